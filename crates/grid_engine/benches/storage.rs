@@ -0,0 +1,92 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use grid_engine::grid_engine::GridEngine;
+use grid_engine::storage::{CellStorage, DenseGrid, SparseGrid, StorageKind};
+
+const ROWS: usize = 200;
+const COLS: usize = 24;
+
+/// Fills the grid with 4x4 items, leaving every other slot of 4 columns empty
+fn fill<S: CellStorage>(grid: &mut S) {
+    for y in (0..ROWS).step_by(4) {
+        for x in (0..COLS).step_by(8) {
+            let id = format!("{}-{}", x, y);
+            for cell_y in y..y + 4 {
+                for cell_x in x..x + 4 {
+                    grid.set(cell_y, cell_x, Some(&id));
+                }
+            }
+        }
+    }
+}
+
+fn bench_storage<S: CellStorage>(c: &mut Criterion, name: &str, new: impl Fn() -> S) {
+    let mut group = c.benchmark_group("storage");
+
+    group.bench_function(BenchmarkId::new("fill", name), |b| {
+        b.iter(|| {
+            let mut grid = new();
+            fill(&mut grid);
+            grid
+        })
+    });
+
+    let mut grid = new();
+    fill(&mut grid);
+    group.bench_function(BenchmarkId::new("get", name), |b| {
+        b.iter(|| {
+            (0..ROWS)
+                .filter(|y| grid.get(black_box(*y), black_box(y % COLS)) != Some(&None))
+                .count()
+        })
+    });
+    group.bench_function(BenchmarkId::new("collisions", name), |b| {
+        b.iter(|| {
+            (0..ROWS - 6)
+                .map(|y| grid.collisions(black_box(2), black_box(y), 6, 6, "").len())
+                .sum::<usize>()
+        })
+    });
+
+    group.finish();
+}
+
+fn storage(c: &mut Criterion) {
+    bench_storage(c, "dense", || DenseGrid::new(ROWS, COLS));
+    bench_storage(c, "sparse", || SparseGrid::new(ROWS, COLS));
+}
+
+fn engine(c: &mut Criterion) {
+    let mut group = c.benchmark_group("engine");
+
+    for (name, kind) in [
+        ("dense", StorageKind::Dense),
+        ("sparse", StorageKind::Sparse),
+    ] {
+        let new_engine = || {
+            let mut engine = GridEngine::with_storage(ROWS, COLS, kind);
+            for y in (0..ROWS).step_by(4) {
+                for x in (0..COLS).step_by(8) {
+                    engine.add_item(format!("{}-{}", x, y), x, y, 4, 4).unwrap();
+                }
+            }
+            engine
+        };
+
+        // Drags an item down, pushing every item below it, on a fresh engine each time
+        group.bench_function(BenchmarkId::new("move_item", name), |b| {
+            b.iter_batched(
+                new_engine,
+                |mut engine| {
+                    engine.move_item("0-100", 0, 102).unwrap();
+                    engine
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, storage, engine);
+criterion_main!(benches);
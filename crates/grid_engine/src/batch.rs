@@ -0,0 +1,190 @@
+//! Utilities over batches of changes, to keep them compact before storing or sending them,
+//! like the many moves of a node being dragged.
+
+use std::collections::HashMap;
+
+use crate::diff::{diff_values, grid_resizes};
+use crate::error::GridError;
+use crate::grid_engine::{
+    AddChangeData, Change, MoveChangeData, NestedChangeData, Node, RemoveChangeData,
+    ResizeChangeData, UpdateDataChangeData,
+};
+
+/// Gets the batch that reverts `changes`, undoing them from the last to the first
+pub fn invert(changes: &[Change]) -> Vec<Change> {
+    changes.iter().rev().map(Change::inverse).collect()
+}
+
+/// Merges the changes of each item into the fewest changes going from its value before the
+/// batch to its value after it, like the diff of both states:
+/// - an `Add` followed by moves becomes a single `Add` at the last position
+/// - consecutive moves become a single `Move`
+/// - an `Add` followed by a `Remove` cancels out
+/// - grid resizes become at most a growth before the item changes and a shrink after them
+///
+/// Applied to the active layout, the squashed batch gives the same grid as `changes`. Stored
+/// layouts may place an added item elsewhere, as they only see its last position.
+/// Fails if the nested grid of an item does not fit in it.
+pub fn squash(changes: &[Change]) -> Result<Vec<Change>, GridError> {
+    // Value of each item before and after the batch, in order of their first change
+    let mut ids: Vec<&str> = Vec::new();
+    let mut values: HashMap<&str, (Option<&Node>, Option<&Node>)> = HashMap::new();
+    let mut sizes: Option<((usize, usize), (usize, usize))> = None;
+
+    for change in changes {
+        let (old_value, new_value) = match change {
+            Change::Add(AddChangeData { value }) => (None, Some(value)),
+            Change::Remove(RemoveChangeData { value }) => (Some(value), None),
+            Change::Move(MoveChangeData {
+                old_value,
+                new_value,
+            })
+            | Change::Resize(ResizeChangeData {
+                old_value,
+                new_value,
+            })
+            | Change::UpdateData(UpdateDataChangeData {
+                old_value,
+                new_value,
+            })
+            | Change::Nested(NestedChangeData {
+                old_value,
+                new_value,
+                ..
+            }) => (Some(old_value), Some(new_value)),
+            Change::GridResize(data) => {
+                let first = sizes.map_or((data.old_rows, data.old_cols), |(first, _)| first);
+                sizes = Some((first, (data.new_rows, data.new_cols)));
+                continue;
+            }
+        };
+
+        let id = old_value.or(new_value).map_or("", |node| node.id.as_str());
+        values
+            .entry(id)
+            .and_modify(|(_, last)| *last = new_value)
+            .or_insert_with(|| {
+                ids.push(id);
+                (old_value, new_value)
+            });
+    }
+
+    let [grow, shrink] = match sizes {
+        Some((from, to)) => grid_resizes(from, to),
+        None => [None, None],
+    };
+    let values: Vec<(Option<&Node>, Option<&Node>)> = ids.iter().map(|id| values[id]).collect();
+    let mut squashed: Vec<Change> = grow.into_iter().collect();
+    diff_values(&values, &mut squashed)?;
+    squashed.extend(shrink);
+    Ok(squashed)
+}
+
+/// Merges a batch with the one applied right after it into a single squashed batch
+pub fn compose(first: &[Change], second: &[Change]) -> Result<Vec<Change>, GridError> {
+    squash(&[first, second].concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::grid_engine::GridEngine;
+    use crate::grid_view::GridView;
+    use crate::test_utils::{operations_strategy, record_batches, run_operations, view_strategy};
+
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn test_batch_round_trip(from in view_strategy(), operations in operations_strategy()) {
+            let mut engine = GridEngine::from(&from);
+            let batches = record_batches(&mut engine);
+            run_operations(&mut engine, operations);
+            let batches: Vec<_> = batches
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|batch| batch.changes.clone())
+                .collect();
+            let changes = batches.concat();
+
+            // The squashed batch gives the same grid. Stored layouts place the items added back
+            // at their first free position, so only the active layout is compared.
+            let active_layout = |mut view: GridView| {
+                view.layouts.clear();
+                view.serialized_as_str()
+            };
+            let squashed = squash(&changes).unwrap();
+            let mut squashed_engine = GridEngine::from(&from);
+            squashed_engine.apply_changes(&squashed).unwrap();
+            prop_assert_eq!(
+                active_layout(squashed_engine.get_grid_view()),
+                active_layout(engine.get_grid_view())
+            );
+            prop_assert_eq!(&squash(&squashed).unwrap(), &squashed);
+
+            // Composing the first half of the batches with the second one gives the same batch
+            let (first, second) = batches.split_at(batches.len() / 2);
+            prop_assert_eq!(
+                compose(&first.concat(), &second.concat()).unwrap(),
+                squashed
+            );
+
+            // The inverted batch reverts it
+            engine.apply_changes(&invert(&changes)).unwrap();
+            prop_assert_eq!(active_layout(engine.get_grid_view()), active_layout(from));
+        }
+    }
+
+    #[test]
+    fn test_batch_squash() {
+        let node = |id: &str, x, y| Node::new(id.to_string(), x, y, 1, 1);
+        let add = |id, x, y| {
+            Change::Add(AddChangeData {
+                value: node(id, x, y),
+            })
+        };
+        let moved = |id, (x, y), (new_x, new_y)| {
+            Change::Move(MoveChangeData {
+                old_value: node(id, x, y),
+                new_value: node(id, new_x, new_y),
+            })
+        };
+
+        // Dragging items around, one change at a time
+        let changes = vec![
+            add("a", 0, 0),
+            moved("a", (0, 0), (1, 0)),
+            moved("a", (1, 0), (2, 0)),
+            moved("b", (0, 1), (1, 1)),
+            moved("b", (1, 1), (2, 1)),
+            add("c", 0, 2),
+            Change::Remove(RemoveChangeData {
+                value: node("c", 0, 2),
+            }),
+            moved("d", (0, 3), (1, 3)),
+            moved("d", (1, 3), (0, 3)),
+        ];
+        let squashed = squash(&changes).unwrap();
+        assert_eq!(squashed, vec![moved("b", (0, 1), (2, 1)), add("a", 2, 0)]);
+        assert_eq!(compose(&changes[..4], &changes[4..]).unwrap(), squashed);
+        assert_eq!(
+            invert(&squashed),
+            vec![
+                Change::Remove(RemoveChangeData {
+                    value: node("a", 2, 0),
+                }),
+                moved("b", (2, 1), (0, 1)),
+            ]
+        );
+
+        let mut engine = GridEngine::new(4, 4);
+        engine.add_item("b".to_string(), 0, 1, 1, 1).unwrap();
+        engine.add_item("d".to_string(), 0, 3, 1, 1).unwrap();
+        let mut squashed_engine = GridEngine::from(&engine.get_grid_view());
+        engine.apply_changes(&changes).unwrap();
+        squashed_engine.apply_changes(&squashed).unwrap();
+        assert_eq!(squashed_engine.get_hash(), engine.get_hash());
+    }
+}
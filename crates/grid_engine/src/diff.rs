@@ -0,0 +1,483 @@
+//! Changes turning a grid into another one, like a saved template, so it can be applied to a
+//! live grid as a regular batch instead of replacing it.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+use tsify_next::Tsify;
+
+use crate::error::GridError;
+use crate::grid_engine::{
+    check_updatable, only_layout_differs, reflow_child, AddChangeData, Change, GridEngine,
+    GridResizeChangeData, Layout, MoveChangeData, NestedChangeData, Node, RemoveChangeData,
+    ResizeChangeData, UpdateDataChangeData,
+};
+use crate::grid_view::GridView;
+use crate::storage::CellStorage;
+
+/// Changes turning a view into another one, its active layout first, then its stored layouts
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Tsify)]
+pub struct ViewDiff {
+    /// Changes of the active layout, to be applied first with `apply_changes`
+    pub changes: Vec<Change>,
+    /// Changes of each stored layout, to be applied then with `apply_layout_changes`
+    pub layouts: Vec<LayoutChanges>,
+    /// Stored layouts the changes can not reach, which are left as they are
+    pub skipped_layouts: Vec<String>,
+}
+
+impl ViewDiff {
+    /// Whether both views are the same
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.layouts.is_empty() && self.skipped_layouts.is_empty()
+    }
+}
+
+/// Changes of a stored layout, moving and resizing its items
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Tsify)]
+pub struct LayoutChanges {
+    pub layout: String,
+    pub changes: Vec<Change>,
+}
+
+/// Gets the changes that turn `from` into `to`, the ones of the active layouts, see
+/// `diff_grid`, then the ones of the stored layouts with the same name, see `diff_layout`.
+///
+/// A stored layout is skipped when only one of the views has it, or when moving and resizing
+/// its items does not reach the one of `to`, since the other changes of a batch apply to every
+/// layout. It happens when an item is locked, or no_move, in another position than the one it
+/// gets once the active changes are applied, or has other data or other nested items than in
+/// the active layout.
+/// Fails if a nested grid of either view does not fit in its item, or if the active changes do
+/// not apply to `from`.
+pub fn diff(from: &GridView, to: &GridView) -> Result<ViewDiff, GridError> {
+    let changes = diff_grid(from, to)?;
+
+    let names: BTreeSet<&String> = from.layouts.keys().chain(to.layouts.keys()).collect();
+    let mut layouts = Vec::new();
+    let mut skipped_layouts = Vec::new();
+    // Stored layouts get the changes of the active one, so they are compared once applied
+    let compared = names
+        .iter()
+        .any(|name| from.layouts.contains_key(*name) && to.layouts.contains_key(*name));
+    let engine = if compared {
+        let mut engine = GridEngine::from(from);
+        engine.apply_changes(&changes)?;
+        Some(engine)
+    } else {
+        None
+    };
+
+    for name in names {
+        let layout = engine.as_ref().and_then(|engine| engine.layouts.get(name));
+        let layout_changes = match (layout, to.layouts.get(name)) {
+            (Some(layout), Some(target)) => diff_layout(layout, target),
+            _ => None,
+        };
+        match layout_changes {
+            Some(changes) if changes.is_empty() => {}
+            Some(changes) => layouts.push(LayoutChanges {
+                layout: name.clone(),
+                changes,
+            }),
+            None => skipped_layouts.push(name.clone()),
+        }
+    }
+
+    Ok(ViewDiff {
+        changes,
+        layouts,
+        skipped_layouts,
+    })
+}
+
+/// Gets the changes that turn the active layout of `from` into the one of `to`, to be applied
+/// with `apply_changes`:
+/// - a `GridResize` growing the grid first, if it grows in any direction
+/// - a `Remove` for each item missing from `to`, with other flags or constraints, or whose
+///   flags do not let it get its new position or size
+/// - a `Move`, or a `Resize`, for each item with another position or size, carrying its nested
+///   grid reflowed, then a `Nested` change when the items of its nested grid differ
+/// - an `UpdateData` for each item with other data
+/// - an `Add` for each item missing from `from`, or replaced
+/// - a `GridResize` shrinking the grid last, if it shrinks in any direction
+///
+/// Fails if a nested grid of either view does not fit in its item.
+pub(crate) fn diff_grid(from: &GridView, to: &GridView) -> Result<Vec<Change>, GridError> {
+    let mut values: Vec<(Option<&Node>, Option<&Node>)> = from
+        .items
+        .iter()
+        .map(|(id, node)| (Some(node), to.items.get(id)))
+        .collect();
+    values.extend(
+        to.items
+            .iter()
+            .filter(|(id, _)| !from.items.contains_key(*id))
+            .map(|(_, node)| (None, Some(node))),
+    );
+
+    let [grow, shrink] = grid_resizes(from.grid.size(), to.grid.size());
+    let mut changes: Vec<Change> = grow.into_iter().collect();
+    diff_values(&values, &mut changes)?;
+    changes.extend(shrink);
+
+    Ok(changes)
+}
+
+/// Resizes the grid from `from` to `to` in two steps, growing it in every direction it grows
+/// before any item changes, and shrinking it after all of them, so every item stays in bounds
+pub(crate) fn grid_resizes(
+    (from_rows, from_cols): (usize, usize),
+    (to_rows, to_cols): (usize, usize),
+) -> [Option<Change>; 2] {
+    let resize = |(old_rows, old_cols), (new_rows, new_cols)| {
+        Change::GridResize(GridResizeChangeData {
+            old_rows,
+            old_cols,
+            new_rows,
+            new_cols,
+        })
+    };
+    let (from, to) = ((from_rows, from_cols), (to_rows, to_cols));
+    let largest = (from_rows.max(to_rows), from_cols.max(to_cols));
+    let grow = (largest != from).then(|| resize(from, largest));
+    let shrink = (largest != to).then(|| resize(largest, to));
+    [grow, shrink]
+}
+
+/// Whether changes can turn `node` into `new_node`. They never change the flags or the
+/// constraints of an item, nor whether it has a nested grid, and never move a locked or no_move
+/// item, so it is replaced instead.
+fn is_updatable(node: &Node, new_node: &Node) -> bool {
+    let unchanged = Node {
+        x: node.x,
+        y: node.y,
+        w: node.w,
+        h: node.h,
+        data: node.data.clone(),
+        child: node.child.clone(),
+        ..new_node.clone()
+    };
+    unchanged == *node
+        && node.child.is_some() == new_node.child.is_some()
+        && check_updatable(node, new_node.x, new_node.y, new_node.w, new_node.h).is_ok()
+}
+
+/// Pushes the changes turning each item from its old value into its new one, where a missing
+/// value is an item to add or to remove. Removes come first, so the other changes can use
+/// their cells, and adds last.
+pub(crate) fn diff_values(
+    values: &[(Option<&Node>, Option<&Node>)],
+    changes: &mut Vec<Change>,
+) -> Result<(), GridError> {
+    for value in values.iter() {
+        match *value {
+            (Some(node), None) => changes.push(Change::Remove(RemoveChangeData {
+                value: node.clone(),
+            })),
+            (Some(node), Some(new_node)) if !is_updatable(node, new_node) => {
+                changes.push(Change::Remove(RemoveChangeData {
+                    value: node.clone(),
+                }))
+            }
+            _ => {}
+        }
+    }
+
+    for value in values.iter() {
+        if let (Some(node), Some(new_node)) = *value {
+            if is_updatable(node, new_node) {
+                diff_node(node, new_node, changes)?;
+            }
+        }
+    }
+
+    // Replaced items are added back before the new ones, as they come first once the changes
+    // are squashed again
+    for value in values.iter() {
+        if let (Some(node), Some(new_node)) = *value {
+            if !is_updatable(node, new_node) {
+                changes.push(Change::Add(AddChangeData {
+                    value: new_node.clone(),
+                }));
+            }
+        }
+    }
+
+    for value in values.iter() {
+        if let (None, Some(node)) = *value {
+            changes.push(Change::Add(AddChangeData {
+                value: node.clone(),
+            }));
+        }
+    }
+
+    Ok(())
+}
+
+/// Pushes the changes turning `node` into `new_node`, the same item in another state
+fn diff_node(node: &Node, new_node: &Node, changes: &mut Vec<Change>) -> Result<(), GridError> {
+    let mut node = node.clone();
+
+    if (node.x, node.y, node.w, node.h) != (new_node.x, new_node.y, new_node.w, new_node.h) {
+        let moved = Node {
+            x: new_node.x,
+            y: new_node.y,
+            w: new_node.w,
+            h: new_node.h,
+            ..node.clone()
+        };
+        // The nested grid is reflowed the way the engine does it, unless its items only moved
+        let with_new_child = Node {
+            child: new_node.child.clone(),
+            ..moved.clone()
+        };
+        let moved = if only_layout_differs(&node, &with_new_child) {
+            with_new_child
+        } else {
+            reflow_child(&node, moved)?
+        };
+
+        if node.w == moved.w && node.h == moved.h {
+            changes.push(Change::Move(MoveChangeData {
+                old_value: node.clone(),
+                new_value: moved.clone(),
+            }));
+        } else {
+            changes.push(Change::Resize(ResizeChangeData {
+                old_value: node.clone(),
+                new_value: moved.clone(),
+            }));
+        }
+        node = moved;
+    }
+
+    if node.child != new_node.child {
+        if let (Some(child), Some(new_child)) = (node.get_child_grid()?, new_node.get_child_grid()?)
+        {
+            let nested = Node {
+                child: new_node.child.clone(),
+                ..node.clone()
+            };
+            changes.push(Change::Nested(NestedChangeData {
+                changes: diff_grid(&child.get_grid_view(), &new_child.get_grid_view())?,
+                old_value: node,
+                new_value: nested.clone(),
+            }));
+            node = nested;
+        }
+    }
+
+    if node.data != new_node.data {
+        changes.push(Change::UpdateData(UpdateDataChangeData {
+            old_value: node,
+            new_value: new_node.clone(),
+        }));
+    }
+
+    Ok(())
+}
+
+/// Gets the changes that turn a stored layout into `target`, which only move and resize its
+/// items and resize its grid, `None` if they can not. The layouts must have the same items,
+/// differing in their position, their size or the layout of their nested grid.
+fn diff_layout(layout: &Layout, target: &Layout) -> Option<Vec<Change>> {
+    if !layout.items.keys().eq(target.items.keys()) {
+        return None;
+    }
+
+    let [grow, shrink] = grid_resizes(layout.grid.size(), target.grid.size());
+    let mut changes: Vec<Change> = grow.into_iter().collect();
+    for (node, new_node) in layout.items.values().zip(target.items.values()) {
+        if node == new_node {
+            continue;
+        }
+        if !only_layout_differs(node, new_node)
+            || check_updatable(node, new_node.x, new_node.y, new_node.w, new_node.h).is_err()
+        {
+            return None;
+        }
+
+        if node.w == new_node.w && node.h == new_node.h {
+            changes.push(Change::Move(MoveChangeData {
+                old_value: node.clone(),
+                new_value: new_node.clone(),
+            }));
+        } else {
+            changes.push(Change::Resize(ResizeChangeData {
+                old_value: node.clone(),
+                new_value: new_node.clone(),
+            }));
+        }
+    }
+    changes.extend(shrink);
+
+    Some(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use proptest::test_runner::TestCaseError;
+
+    use crate::grid_engine::{GridEngine, DEFAULT_LAYOUT};
+    use crate::test_utils::{operations_strategy, run_operations, view_strategy};
+
+    use super::*;
+
+    /// Checks that the diff turns `from` into `to`, but for the skipped layouts, with at most
+    /// one change per item, one more for other data and one more for other nested items, and
+    /// two grid resizes
+    fn check_diff(from: &GridView, to: &GridView) -> Result<ViewDiff, TestCaseError> {
+        let diff = from.diff(to).unwrap();
+        let mut engine = GridEngine::from(from);
+        engine.apply_changes(&diff.changes).unwrap();
+        for layout in diff.layouts.iter() {
+            engine
+                .apply_layout_changes(&layout.layout, &layout.changes)
+                .unwrap();
+        }
+        if diff.skipped_layouts.is_empty() {
+            prop_assert_eq!(engine.get_hash(), to.hash());
+        }
+        let (mut view, mut to) = (engine.get_grid_view(), to.clone());
+        for name in diff.skipped_layouts.iter() {
+            view.layouts.remove(name);
+            to.layouts.remove(name);
+        }
+        prop_assert_eq!(view.serialized_as_str(), to.serialized_as_str());
+        let changes = &diff.changes;
+
+        // A moved item with other nested items gets a `Nested` change too, and an item with
+        // other flags is removed and added back
+        let changed: usize = to
+            .items
+            .iter()
+            .filter(|(id, node)| from.items.get(*id) != Some(node))
+            .map(|(id, node)| match from.items.get(id) {
+                Some(old_node) if old_node.get_flags() == node.get_flags() => {
+                    1 + usize::from(old_node.data != node.data)
+                        + usize::from(old_node.child != node.child)
+                }
+                _ => 2,
+            })
+            .sum();
+        let removed = from
+            .items
+            .keys()
+            .filter(|id| !to.items.contains_key(*id))
+            .count();
+        prop_assert!(changes.len() <= changed + removed + 2);
+        prop_assert!(to.diff(&to).unwrap().is_empty());
+        Ok(diff)
+    }
+
+    proptest! {
+        #[test]
+        fn test_diff_round_trip(from in view_strategy(), to in view_strategy()) {
+            check_diff(&from, &to)?;
+        }
+
+        #[test]
+        fn test_diff_round_trip_after_operations(
+            from in view_strategy(),
+            operations in operations_strategy(),
+        ) {
+            let mut engine = GridEngine::from(&from);
+            run_operations(&mut engine, operations);
+            // Operations keep every layout reachable
+            let diff = check_diff(&from, &engine.get_grid_view())?;
+            prop_assert!(diff.skipped_layouts.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut from = GridEngine::new(4, 6);
+        from.add_item("moved".to_string(), 0, 0, 2, 2).unwrap();
+        from.add_item("resized".to_string(), 2, 0, 1, 1).unwrap();
+        from.add_item("removed".to_string(), 4, 0, 2, 2).unwrap();
+        from.add_node(Node::new("tabs".to_string(), 0, 2, 3, 2).with_child_grid())
+            .unwrap();
+        from.update_child("tabs", |tabs| {
+            tabs.add_item("a".to_string(), 0, 0, 1, 1).map(|_| ())
+        })
+        .unwrap();
+
+        let mut to = GridEngine::from(&from.get_grid_view());
+        to.remove_item("removed").unwrap();
+        to.move_item("moved", 4, 0).unwrap();
+        to.update_item("resized", 2, 0, 2, 2).unwrap();
+        to.update_item_data("resized", serde_json::json!("chart"))
+            .unwrap();
+        to.update_child("tabs", |tabs| tabs.move_item("a", 2, 1))
+            .unwrap();
+        to.add_item("added".to_string(), 0, 4, 2, 2).unwrap();
+
+        let changes = from
+            .get_grid_view()
+            .diff(&to.get_grid_view())
+            .unwrap()
+            .changes;
+        let kinds: Vec<&str> = changes
+            .iter()
+            .map(|change| match change {
+                Change::Add(_) => "Add",
+                Change::Remove(_) => "Remove",
+                Change::Move(_) => "Move",
+                Change::Resize(_) => "Resize",
+                Change::UpdateData(_) => "UpdateData",
+                Change::Nested(_) => "Nested",
+                Change::GridResize(_) => "GridResize",
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "GridResize",
+                "Remove",
+                "Move",
+                "Resize",
+                "UpdateData",
+                "Nested",
+                "Add"
+            ]
+        );
+
+        from.apply_changes(&changes).unwrap();
+        assert_eq!(
+            from.get_grid_view().serialized_as_str(),
+            to.get_grid_view().serialized_as_str()
+        );
+        assert_eq!(from.get_hash(), to.get_hash());
+        assert!(from
+            .get_grid_view()
+            .diff(&to.get_grid_view())
+            .unwrap()
+            .is_empty());
+
+        // Stored layouts get their own changes, once the active ones are applied
+        from.add_layout("sm", 3).unwrap();
+        let mut to = GridEngine::from(&from.get_grid_view());
+        to.remove_item("added").unwrap();
+        to.set_layout("sm").unwrap();
+        to.move_item("moved", 0, 20).unwrap();
+        to.set_layout(DEFAULT_LAYOUT).unwrap();
+        to.add_layout("lg", 12).unwrap();
+
+        let diff = from.get_grid_view().diff(&to.get_grid_view()).unwrap();
+        assert_eq!(diff.changes.len(), 1);
+        assert_eq!(diff.layouts.len(), 1);
+        assert_eq!(diff.layouts[0].layout, "sm");
+        assert_eq!(diff.skipped_layouts, vec!["lg".to_string()]);
+        from.apply_changes(&diff.changes).unwrap();
+        from.apply_layout_changes("sm", &diff.layouts[0].changes)
+            .unwrap();
+        from.add_layout("lg", 12).unwrap();
+        assert_eq!(
+            from.get_grid_view().serialized_as_str(),
+            to.get_grid_view().serialized_as_str()
+        );
+        assert_eq!(from.get_hash(), to.get_hash());
+    }
+}
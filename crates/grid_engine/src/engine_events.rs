@@ -10,7 +10,7 @@ impl<T: Eq + PartialEq + Hash + Debug> EventNameTrait for T {}
 pub trait EventValueTrait: Eq + Hash {}
 impl<T: Eq + Hash> EventValueTrait for T {}
 
-type Function<EventValue> = dyn FnMut(&GridView, &EventValue) -> () + Send + 'static + Sync;
+type Function<EventValue> = dyn FnMut(&GridView, &EventValue) + Send + 'static + Sync;
 
 struct ListenerFunction<Event: EventValueTrait> {
     id: String,
@@ -51,10 +51,7 @@ impl<EventName: EventNameTrait, EventValue: EventValueTrait> EventListener<Event
             id: id.clone(),
             function,
         };
-        self.listeners
-            .entry(event)
-            .or_insert_with(Vec::new)
-            .push(listener);
+        self.listeners.entry(event).or_default().push(listener);
         id
     }
 
@@ -68,6 +65,11 @@ impl<EventName: EventNameTrait, EventValue: EventValueTrait> EventListener<Event
         }
     }
 
+    /// Whether the event has any listener, to skip building its value when it has none
+    pub fn has_listeners(&self, event: &EventName) -> bool {
+        self.listeners.contains_key(event)
+    }
+
     pub fn trigger_event(
         &mut self,
         grid: &GridView,
@@ -76,7 +78,7 @@ impl<EventName: EventNameTrait, EventValue: EventValueTrait> EventListener<Event
     ) {
         if let Some(listeners) = self.listeners.get_mut(&event_name) {
             for listener in listeners {
-                (listener.function)(&grid, &event_value);
+                (listener.function)(grid, &event_value);
             }
         }
     }
@@ -152,8 +154,10 @@ mod tests {
             }),
         );
         assert_eq!(event_listener.listeners.len(), 1);
+        assert!(event_listener.has_listeners(&event_name));
         event_listener.remove_listener(event_name.clone(), &listener_id);
         assert_eq!(event_listener.listeners.len(), 0);
+        assert!(!event_listener.has_listeners(&event_name));
     }
 
     #[test]
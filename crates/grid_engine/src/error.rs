@@ -1,22 +1,290 @@
 use std::error::Error;
+use std::fmt::Display;
+use std::string::FromUtf8Error;
 
+/// Errors of the grid engine operations
 #[derive(Debug)]
-pub struct GridError {
-    message: String,
-    description: String,
-    error: Option<Box<dyn Error>>,
+pub enum GridError {
+    /// No item with the id
+    ItemNotFound {
+        id: String,
+    },
+    /// An item with the id already exists
+    DuplicateId {
+        id: String,
+    },
+    /// A rectangle does not fit inside the grid limits
+    OutOfBounds(OutOfBoundsError),
+    /// A node size does not respect its constraints
+    SizeConstraint(SizeConstraintError),
+    /// Items must be at least 1x1, and grids at least one column wide
+    InvalidSize {
+        w: usize,
+        h: usize,
+    },
+    /// The item is locked, so it can not be "moved" nor "resized", which is the operation
+    Locked {
+        id: String,
+        operation: &'static str,
+    },
+    /// The item is no_move, so it can be resized but not moved
+    NoMove {
+        id: String,
+    },
+    /// The item would land over a locked or no_move item, which can not be pushed away
+    Collision {
+        id: String,
+        obstacle_id: String,
+    },
+    /// The item has no grid nested in it
+    NoChildGrid {
+        id: String,
+    },
+    /// Ids of nested items can not contain the path separator
+    InvalidId {
+        id: String,
+    },
+    /// Two items of a grid share cells
+    Overlap {
+        id: String,
+        other_id: String,
+    },
+    /// A change of a batch conflicts with the current grid
+    InvalidChange(ChangeConflictError),
+    /// A serialized grid or event could not be read
+    Deserialize(serde_json::Error),
+    /// Serialized bytes are not valid UTF-8
+    Encoding(FromUtf8Error),
+    LayoutNotFound {
+        name: String,
+    },
+    DuplicateLayout {
+        name: String,
+    },
+    /// The active layout can not be removed
+    ActiveLayout {
+        name: String,
+    },
+    /// The operation can not run within a transaction
+    TransactionInProgress,
+    NoTransaction,
+    /// The grid and the items are out of sync, which should never happen
+    Internal {
+        description: String,
+    },
 }
 
 impl GridError {
-    pub fn new(message: &str, description: &str, error: Option<Box<dyn Error>>) -> GridError {
-        GridError {
-            message: message.to_string(),
-            description: description.to_string(),
-            error,
+    /// Name of the variant, also used as the `name` of the errors thrown to JS
+    pub fn kind(&self) -> &'static str {
+        match self {
+            GridError::ItemNotFound { .. } => "ItemNotFound",
+            GridError::DuplicateId { .. } => "DuplicateId",
+            GridError::OutOfBounds(_) => "OutOfBounds",
+            GridError::SizeConstraint(_) => "SizeConstraint",
+            GridError::InvalidSize { .. } => "InvalidSize",
+            GridError::Locked { .. } => "Locked",
+            GridError::NoMove { .. } => "NoMove",
+            GridError::Collision { .. } => "Collision",
+            GridError::NoChildGrid { .. } => "NoChildGrid",
+            GridError::InvalidId { .. } => "InvalidId",
+            GridError::Overlap { .. } => "Overlap",
+            GridError::InvalidChange(_) => "InvalidChange",
+            GridError::Deserialize(_) => "Deserialize",
+            GridError::Encoding(_) => "Encoding",
+            GridError::LayoutNotFound { .. } => "LayoutNotFound",
+            GridError::DuplicateLayout { .. } => "DuplicateLayout",
+            GridError::ActiveLayout { .. } => "ActiveLayout",
+            GridError::TransactionInProgress => "TransactionInProgress",
+            GridError::NoTransaction => "NoTransaction",
+            GridError::Internal { .. } => "Internal",
         }
     }
 
+    /// Gets the error followed by its sources, as a single line
     pub fn get_message(&self) -> String {
-        format!("{}: {}", self.message, self.error.as_ref().unwrap())
+        let mut message = self.to_string();
+        let mut source = self.source();
+        while let Some(err) = source {
+            message.push_str(&format!(": {}", err));
+            source = err.source();
+        }
+        message
+    }
+}
+
+impl Display for GridError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GridError::ItemNotFound { id } => write!(f, "Item {} not found", id),
+            GridError::DuplicateId { id } => write!(f, "Item {} already exists", id),
+            GridError::OutOfBounds(err) => write!(f, "Out of bounds: {}", err),
+            GridError::SizeConstraint(err) => write!(f, "Size constraint violated: {}", err),
+            GridError::InvalidSize { w, h } => {
+                write!(f, "Invalid size W:{},H:{}, it must be at least 1x1", w, h)
+            }
+            GridError::Locked { id, operation } => {
+                write!(f, "Item {} is locked and can not be {}", id, operation)
+            }
+            GridError::NoMove { id } => write!(f, "Item {} is no_move and can not be moved", id),
+            GridError::Collision { id, obstacle_id } => write!(
+                f,
+                "Item {} can not be placed over the fixed item {}",
+                id, obstacle_id
+            ),
+            GridError::NoChildGrid { id } => write!(f, "Item {} has no child grid", id),
+            GridError::InvalidId { id } => write!(f, "Item id {} can not contain '/'", id),
+            GridError::Overlap { id, other_id } => {
+                write!(f, "Item {} overlaps item {}", id, other_id)
+            }
+            GridError::InvalidChange(err) => write!(f, "{}", err),
+            GridError::Deserialize(_) => write!(f, "Failed to deserialize"),
+            GridError::Encoding(_) => write!(f, "Failed to convert bytes to string"),
+            GridError::LayoutNotFound { name } => write!(f, "Layout {} not found", name),
+            GridError::DuplicateLayout { name } => write!(f, "Layout {} already exists", name),
+            GridError::ActiveLayout { name } => write!(f, "Layout {} is active", name),
+            GridError::TransactionInProgress => write!(
+                f,
+                "Transaction in progress, commit or rollback the transaction first"
+            ),
+            GridError::NoTransaction => write!(f, "No transaction in progress"),
+            GridError::Internal { description } => write!(f, "Internal error: {}", description),
+        }
+    }
+}
+
+impl Error for GridError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GridError::InvalidChange(err) => err.source(),
+            GridError::Deserialize(err) => Some(err),
+            GridError::Encoding(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<OutOfBoundsError> for GridError {
+    fn from(err: OutOfBoundsError) -> Self {
+        GridError::OutOfBounds(err)
+    }
+}
+
+impl From<SizeConstraintError> for GridError {
+    fn from(err: SizeConstraintError) -> Self {
+        GridError::SizeConstraint(err)
+    }
+}
+
+impl From<ChangeConflictError> for GridError {
+    fn from(err: ChangeConflictError) -> Self {
+        GridError::InvalidChange(err)
+    }
+}
+
+impl From<serde_json::Error> for GridError {
+    fn from(err: serde_json::Error) -> Self {
+        GridError::Deserialize(err)
+    }
+}
+
+impl From<FromUtf8Error> for GridError {
+    fn from(err: FromUtf8Error) -> Self {
+        GridError::Encoding(err)
+    }
+}
+
+/// A rectangle that does not fit inside the grid limits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutOfBoundsError {
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
+    pub rows: usize,
+    pub cols: usize,
+}
+
+impl Display for OutOfBoundsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Rectangle X:{},Y:{},W:{},H:{} does not fit in the {}x{} grid",
+            self.x, self.y, self.w, self.h, self.rows, self.cols
+        )
+    }
+}
+
+impl Error for OutOfBoundsError {}
+
+/// A node size outside of the node min and max constraints
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeConstraintError {
+    pub id: String,
+    pub w: usize,
+    pub h: usize,
+    pub min_w: Option<usize>,
+    pub max_w: Option<usize>,
+    pub min_h: Option<usize>,
+    pub max_h: Option<usize>,
+}
+
+fn format_range(min: Option<usize>, max: Option<usize>) -> String {
+    let min = min.map(|min| min.to_string()).unwrap_or_default();
+    match max {
+        Some(max) => format!("{}..={}", min, max),
+        None => format!("{}..", min),
+    }
+}
+
+impl Display for SizeConstraintError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Item {} with W:{},H:{} does not respect its constraints W:{},H:{}",
+            self.id,
+            self.w,
+            self.h,
+            format_range(self.min_w, self.max_w),
+            format_range(self.min_h, self.max_h)
+        )
+    }
+}
+
+impl Error for SizeConstraintError {}
+
+/// A change of a batch that can not be applied over the current grid
+#[derive(Debug)]
+pub struct ChangeConflictError {
+    /// Position of the change in the batch
+    pub index: usize,
+    /// Id of the item the change conflicts on, empty for grid resizes
+    pub id: String,
+    pub reason: String,
+    /// The error of the check the change failed, if it comes from one. The reason is its message.
+    pub source: Option<Box<GridError>>,
+}
+
+impl Display for ChangeConflictError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Change {}", self.index)?;
+        if !self.id.is_empty() {
+            write!(f, " on item {}", self.id)?;
+        }
+        write!(f, " can not be applied")?;
+
+        // The reason of a failed check is shown by its source
+        match self.source {
+            Some(_) => Ok(()),
+            None => write!(f, ": {}", self.reason),
+        }
+    }
+}
+
+impl Error for ChangeConflictError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|err| err as &(dyn Error + 'static))
     }
 }
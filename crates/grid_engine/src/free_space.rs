@@ -0,0 +1,215 @@
+//! Analysis of the empty cells of a grid, to know which item sizes can be placed without
+//! pushing other items.
+
+use serde::{Deserialize, Serialize};
+
+use crate::query::Rect;
+use crate::storage::{CellStorage, Cells};
+
+/// Largest sizes that fit with their top-left corner on each cell, by row and column
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FitMap {
+    /// Sizes of each cell, as `(w, h)`, one per height where the width shrinks, sorted by
+    /// decreasing width. Every smaller size of one of them fits too.
+    pub cells: Vec<Vec<Vec<(usize, usize)>>>,
+}
+
+impl FitMap {
+    /// Largest sizes that fit with their top-left corner on the cell, empty if the cell is
+    /// occupied or outside of the grid
+    pub fn sizes_at(&self, x: usize, y: usize) -> &[(usize, usize)] {
+        self.cells
+            .get(y)
+            .and_then(|row| row.get(x))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn fits_at(&self, x: usize, y: usize, w: usize, h: usize) -> bool {
+        self.sizes_at(x, y)
+            .iter()
+            .any(|(max_w, max_h)| w <= *max_w && h <= *max_h)
+    }
+}
+
+fn is_empty(grid: &Cells, x: usize, y: usize) -> bool {
+    grid.get(y, x) == Some(&None)
+}
+
+/// Number of empty cells from each cell to the right, up to the first occupied one
+fn empty_runs(grid: &Cells) -> Vec<Vec<usize>> {
+    let (rows, cols) = grid.size();
+    (0..rows)
+        .map(|y| {
+            let mut runs = vec![0; cols];
+            for x in (0..cols).rev() {
+                if is_empty(grid, x, y) {
+                    runs[x] = 1 + runs.get(x + 1).copied().unwrap_or(0);
+                }
+            }
+            runs
+        })
+        .collect()
+}
+
+/// Empty rectangles that can not grow in any direction, sorted by decreasing area, then from
+/// top to bottom and left to right
+pub(crate) fn maximal_empty_rects(grid: &Cells) -> Vec<Rect> {
+    let (rows, cols) = grid.size();
+    // Number of empty cells from each cell to the top, up to the first occupied one
+    let mut heights = vec![0; cols];
+    let mut rects = Vec::new();
+
+    for y in 0..rows {
+        for (x, height) in heights.iter_mut().enumerate() {
+            *height = if is_empty(grid, x, y) { *height + 1 } else { 0 };
+        }
+
+        // Rectangles with their bottom on this row, as high as the lowest column they cover, so
+        // they can not grow up
+        for left in 0..cols {
+            let mut h = usize::MAX;
+            for right in left..cols {
+                h = h.min(heights[right]);
+                if h == 0 {
+                    break;
+                }
+                let grows_left = left > 0 && heights[left - 1] >= h;
+                let grows_right = right + 1 < cols && heights[right + 1] >= h;
+                let grows_down = y + 1 < rows && (left..=right).all(|x| is_empty(grid, x, y + 1));
+                if !grows_left && !grows_right && !grows_down {
+                    rects.push(Rect {
+                        x: left,
+                        y: y + 1 - h,
+                        w: right + 1 - left,
+                        h,
+                    });
+                }
+            }
+        }
+    }
+
+    rects.sort_by_key(|rect| (usize::MAX - rect.w * rect.h, rect.y, rect.x));
+    rects
+}
+
+pub(crate) fn fit_map(grid: &Cells) -> FitMap {
+    let runs = empty_runs(grid);
+    let cells = runs
+        .iter()
+        .enumerate()
+        .map(|(y, row)| {
+            (0..row.len())
+                .map(|x| {
+                    let mut sizes: Vec<(usize, usize)> = Vec::new();
+                    let mut w = usize::MAX;
+                    for (h, row) in runs[y..].iter().enumerate() {
+                        if row[x] == 0 {
+                            break;
+                        }
+                        // A taller size replaces the last one if it keeps its width
+                        if row[x] >= w {
+                            sizes.pop();
+                        }
+                        w = w.min(row[x]);
+                        sizes.push((w, h + 1));
+                    }
+                    sizes
+                })
+                .collect()
+        })
+        .collect();
+    FitMap { cells }
+}
+
+/// Whether a `w`x`h` item can be placed somewhere without colliding with other items
+pub(crate) fn can_fit(grid: &Cells, w: usize, h: usize) -> bool {
+    let (rows, cols) = grid.size();
+    if w == 0 || h == 0 || w > cols || h > rows {
+        return false;
+    }
+
+    (0..=rows - h)
+        .flat_map(|y| (0..=cols - w).map(move |x| (x, y)))
+        .any(|(x, y)| grid.collisions(x, y, w, h, "").is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid_engine::GridEngine;
+    use crate::test_utils::random_generator;
+
+    use super::*;
+
+    #[test]
+    fn test_free_space() {
+        // 0 0 . . . .
+        // 0 0 . . . .
+        // . . . 1 . .
+        // . . . . . .
+        let mut engine = GridEngine::new(4, 6);
+        engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+        engine.add_item("1".to_string(), 3, 2, 1, 1).unwrap();
+        let grid_view = engine.get_grid_view();
+
+        let rect = |x, y, w, h| Rect { x, y, w, h };
+        assert_eq!(
+            grid_view.maximal_empty_rects(),
+            vec![
+                rect(2, 0, 4, 2),
+                rect(4, 0, 2, 4),
+                rect(0, 2, 3, 2),
+                rect(0, 3, 6, 1),
+                rect(2, 0, 1, 4),
+            ]
+        );
+        assert_eq!(grid_view.largest_empty_rect(), Some(rect(2, 0, 4, 2)));
+
+        let fit_map = grid_view.fit_map();
+        assert_eq!(fit_map.sizes_at(2, 0), &[(4, 2), (1, 4)]);
+        assert_eq!(fit_map.sizes_at(0, 0), &[]);
+        assert_eq!(fit_map.sizes_at(9, 0), &[]);
+        assert!(fit_map.fits_at(2, 0, 3, 2));
+        assert!(!fit_map.fits_at(2, 0, 2, 3));
+
+        assert!(grid_view.can_fit(4, 2));
+        assert!(grid_view.can_fit(2, 4));
+        assert!(grid_view.can_fit(6, 1));
+        assert!(!grid_view.can_fit(3, 3));
+        assert!(!grid_view.can_fit(7, 1));
+        assert!(!grid_view.can_fit(0, 1));
+        assert_eq!(engine.can_fit(3, 3), grid_view.can_fit(3, 3));
+
+        // The analysis agrees with the collisions on random grids
+        let mut random = random_generator(3);
+        for _ in 0..20 {
+            let mut engine = GridEngine::new(5, 6);
+            for id in 0..6 {
+                let (x, y, w, h) = (random(6), random(5), 1 + random(2), 1 + random(2));
+                let _ = engine.add_item(id.to_string(), x, y, w, h);
+            }
+            let grid_view = engine.get_grid_view();
+            let (rows, cols) = engine.grid.size();
+            let fits = |x: usize, y: usize, w: usize, h: usize| {
+                x + w <= cols && y + h <= rows && engine.grid.collisions(x, y, w, h, "").is_empty()
+            };
+
+            let fit_map = grid_view.fit_map();
+            for (w, h) in (1..=cols).flat_map(|w| (1..=rows).map(move |h| (w, h))) {
+                let mut fits_anywhere = false;
+                for (x, y) in (0..cols).flat_map(|x| (0..rows).map(move |y| (x, y))) {
+                    assert_eq!(fit_map.fits_at(x, y, w, h), fits(x, y, w, h));
+                    fits_anywhere |= fits(x, y, w, h);
+                }
+                assert_eq!(grid_view.can_fit(w, h), fits_anywhere);
+            }
+
+            for Rect { x, y, w, h } in grid_view.maximal_empty_rects() {
+                assert!(fits(x, y, w, h));
+                assert!(x == 0 || !fits(x - 1, y, w + 1, h));
+                assert!(y == 0 || !fits(x, y - 1, w, h + 1));
+                assert!(!fits(x, y, w + 1, h));
+                assert!(!fits(x, y, w, h + 1));
+            }
+        }
+    }
+}
@@ -1,9 +1,18 @@
+use crate::batch;
+use crate::engine_events::EventListener;
+use crate::error::{ChangeConflictError, GridError, OutOfBoundsError, SizeConstraintError};
+use crate::free_space;
 use crate::grid_view::GridView;
-use crate::{engine_events::EventListener, error::GridError};
-use grid::Grid;
+use crate::hash;
+use crate::query::{self, Neighbors, Rect};
+use crate::storage::{CellStorage, Cells, StorageKind};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::fmt::Display;
-use std::{collections::BTreeMap, fmt::Debug};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt::Debug,
+};
 use tsify_next::Tsify;
 use wasm_bindgen::prelude::*;
 
@@ -33,34 +42,462 @@ enum UpdateGridOperation {
 }
 
 fn update_grid(
-    grid: &mut Grid<Option<String>>,
+    grid: &mut Cells,
     node: &Node,
     x: usize,
     y: usize,
     operation: UpdateGridOperation,
 ) -> Result<(), GridError> {
-    let element_at_position = grid.get_mut(y, x);
+    let element_at_position = grid.get(y, x);
 
     match element_at_position {
         Some(cell) => {
             match operation {
                 UpdateGridOperation::Add => {
-                    *cell = Some(node.id.to_string());
+                    grid.set(y, x, Some(&node.id));
                 }
                 UpdateGridOperation::Remove => {
-                    if *cell == Some(node.id.to_string()) {
-                        *cell = None;
+                    if cell.as_ref() == Some(&node.id) {
+                        grid.set(y, x, None);
                     }
                 }
             }
             Ok(())
         }
-        None => Err(GridError::new(
-            &format!("Error updating grid with {:?} operation", operation),
-            &format!("No element at position X:{x},Y:{y} in grid: {:?}", grid),
-            None,
+        None => Err(GridError::Internal {
+            description: format!(
+                "No cell at X:{x},Y:{y} to update with the {:?} operation",
+                operation
+            ),
+        }),
+    }
+}
+
+/// Checks that the rectangle is fully inside a grid of the given size
+fn check_bounds(
+    (rows, cols): (usize, usize),
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+) -> Result<(), GridError> {
+    let fits_cols = x.checked_add(w).is_some_and(|right| right <= cols);
+    let fits_rows = y.checked_add(h).is_some_and(|bottom| bottom <= rows);
+
+    if fits_cols && fits_rows {
+        return Ok(());
+    }
+
+    Err(GridError::OutOfBounds(OutOfBoundsError {
+        x,
+        y,
+        w,
+        h,
+        rows,
+        cols,
+    }))
+}
+
+fn check_size(w: usize, h: usize) -> Result<(), GridError> {
+    if w == 0 || h == 0 {
+        return Err(GridError::InvalidSize { w, h });
+    }
+    Ok(())
+}
+
+/// Checks that the node size respects its own min and max constraints
+fn check_constraints(node: &Node) -> Result<(), GridError> {
+    let fits_w = node.min_w.is_none_or(|min_w| node.w >= min_w)
+        && node.max_w.is_none_or(|max_w| node.w <= max_w);
+    let fits_h = node.min_h.is_none_or(|min_h| node.h >= min_h)
+        && node.max_h.is_none_or(|max_h| node.h <= max_h);
+
+    if fits_w && fits_h {
+        return Ok(());
+    }
+
+    Err(GridError::SizeConstraint(SizeConstraintError {
+        id: node.id.clone(),
+        w: node.w,
+        h: node.h,
+        min_w: node.min_w,
+        max_w: node.max_w,
+        min_h: node.min_h,
+        max_h: node.max_h,
+    }))
+}
+
+fn change_conflict(index: usize, id: &str, reason: &str) -> GridError {
+    GridError::InvalidChange(ChangeConflictError {
+        index,
+        id: id.to_string(),
+        reason: reason.to_string(),
+        source: None,
+    })
+}
+
+/// Wraps the error of a check as the conflict of the change at `index`
+fn into_change_conflict(index: usize, id: &str, err: GridError) -> GridError {
+    GridError::InvalidChange(ChangeConflictError {
+        index,
+        id: id.to_string(),
+        reason: err.to_string(),
+        source: Some(Box::new(err)),
+    })
+}
+
+/// Checks the node placed by the change at `index` against the grid size and its constraints
+fn check_changed_node(index: usize, node: &Node, size: (usize, usize)) -> Result<(), GridError> {
+    check_size(node.w, node.h)
+        .and_then(|_| check_bounds(size, node.x, node.y, node.w, node.h))
+        .and_then(|_| check_constraints(node))
+        .map_err(|err| into_change_conflict(index, &node.id, err))
+}
+
+/// Splits the first id of the path from the path to the rest of the nested grids
+fn split_path(path: &str) -> (&str, Option<&str>) {
+    match path.split_once(PATH_SEPARATOR) {
+        Some((id, rest)) => (id, Some(rest)),
+        None => (path, None),
+    }
+}
+
+/// Checks the grid nested in the node placed by the change at `index`, with the grids nested in
+/// its own items
+fn check_changed_child(index: usize, node: &Node) -> Result<(), GridError> {
+    match &node.child {
+        Some(child) => child
+            .validate(node.w)
+            .map_err(|err| into_change_conflict(index, &node.id, err)),
+        None => Ok(()),
+    }
+}
+
+/// Whether `new_node` is `node` moved or resized, with nothing else changed but the layout of
+/// its nested grid, whose items may be moved or resized in the same way
+pub(crate) fn only_layout_differs(node: &Node, new_node: &Node) -> bool {
+    let unchanged = Node {
+        x: node.x,
+        y: node.y,
+        w: node.w,
+        h: node.h,
+        child: None,
+        ..new_node.clone()
+    };
+    if unchanged
+        != (Node {
+            child: None,
+            ..node.clone()
+        })
+    {
+        return false;
+    }
+
+    match (&node.child, &new_node.child) {
+        (Some(child), Some(new_child)) => {
+            child.items.len() == new_child.items.len()
+                && child.items.iter().all(|(id, item)| {
+                    new_child
+                        .items
+                        .get(id)
+                        .is_some_and(|new_item| only_layout_differs(item, new_item))
+                })
+        }
+        (child, new_child) => child.is_none() && new_child.is_none(),
+    }
+}
+
+/// Value of the item in the middle of a batch, from the changes so far or else from the items
+fn current_value<'a>(
+    items: &'a BTreeMap<String, Node>,
+    overrides: &HashMap<&str, Option<&'a Node>>,
+    id: &str,
+) -> Option<&'a Node> {
+    match overrides.get(id) {
+        Some(node) => *node,
+        None => items.get(id),
+    }
+}
+
+/// Checks that the change at `index` starts from the current value of the item
+fn check_current_value(
+    index: usize,
+    current: Option<&Node>,
+    old_value: &Node,
+) -> Result<(), GridError> {
+    match current {
+        Some(current) if current == old_value => Ok(()),
+        Some(_) => Err(change_conflict(
+            index,
+            &old_value.id,
+            "Item does not match the old value",
         )),
+        None => {
+            let err = GridError::ItemNotFound {
+                id: old_value.id.clone(),
+            };
+            Err(into_change_conflict(index, &old_value.id, err))
+        }
+    }
+}
+
+/// Checks that the flags of the node allow it to get the new position and size
+/// Checks that the node can leave its grid, which locked and no_move nodes never do, whatever
+/// their position in the other grid
+fn check_movable(node: &Node) -> Result<(), GridError> {
+    if node.locked {
+        return Err(GridError::Locked {
+            id: node.id.clone(),
+            operation: "moved",
+        });
+    }
+    if node.no_move {
+        return Err(GridError::NoMove {
+            id: node.id.clone(),
+        });
+    }
+    Ok(())
+}
+
+pub(crate) fn check_updatable(
+    node: &Node,
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+) -> Result<(), GridError> {
+    let moves = node.x != x || node.y != y;
+    let resizes = node.w != w || node.h != h;
+
+    if node.locked && (moves || resizes) {
+        return Err(GridError::Locked {
+            id: node.id.clone(),
+            operation: if moves { "moved" } else { "resized" },
+        });
+    }
+    if node.no_move && moves {
+        return Err(GridError::NoMove {
+            id: node.id.clone(),
+        });
     }
+    Ok(())
+}
+
+/// Name of the layout every engine starts with
+pub const DEFAULT_LAYOUT: &str = "default";
+
+pub(crate) fn default_layout() -> String {
+    DEFAULT_LAYOUT.to_string()
+}
+
+pub(crate) fn is_default_layout(layout: &str) -> bool {
+    layout == DEFAULT_LAYOUT
+}
+
+/// Scales the x and w of the node from `old_cols` to `cols`, keeping it inside the grid.
+/// No_move nodes keep their x and locked nodes keep their x and w.
+fn scale_node(node: &Node, old_cols: usize, cols: usize) -> Node {
+    let mut w = node.w;
+    if !node.locked {
+        w = ((node.w * cols + old_cols / 2) / old_cols).max(1);
+        w = w.max(node.min_w.unwrap_or(1));
+        w = w.min(node.max_w.unwrap_or(usize::MAX)).min(cols);
+    }
+
+    let mut x = node.x;
+    if !node.is_immovable() {
+        x = (node.x * cols / old_cols).min(cols.saturating_sub(w));
+    }
+
+    Node {
+        x,
+        w,
+        ..node.clone()
+    }
+}
+
+/// A stored layout, with its own grid and positions for the same items as the active one
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Layout {
+    pub(crate) grid: Cells,
+    pub(crate) items: BTreeMap<String, Node>,
+}
+
+impl Layout {
+    /// Places a copy of the node at the first free position in reading order, with its width
+    /// clamped to the layout columns, growing the layout rows if none fits
+    fn insert_first_fit(&mut self, node: &Node) -> Result<(), GridError> {
+        if self.items.contains_key(&node.id) {
+            return Ok(());
+        }
+
+        let (rows, cols) = self.grid.size();
+        let w = node.w.min(cols);
+        let is_free = |x: usize, y: usize| {
+            (x..x + w)
+                .all(|x| (y..y + node.h).all(|y| matches!(self.grid.get(y, x), None | Some(None))))
+        };
+        let (x, y) = (0..=rows)
+            .flat_map(|y| (0..=cols - w).map(move |x| (x, y)))
+            .find(|&(x, y)| is_free(x, y))
+            .expect("Rows past the grid are always free");
+
+        let node = reflow_child(
+            node,
+            Node {
+                x,
+                y,
+                w,
+                ..node.clone()
+            },
+        )?;
+        self.grid.resize(rows.max(y + node.h), cols);
+        node.for_cell(&mut |x, y| {
+            update_grid(&mut self.grid, &node, x, y, UpdateGridOperation::Add)
+        })?;
+        self.items.insert(node.id.clone(), node);
+        Ok(())
+    }
+
+    fn update_data(&mut self, id: &str, data: &Value) -> Result<(), GridError> {
+        if let Some(node) = self.items.get_mut(id) {
+            node.data = data.clone();
+        }
+        Ok(())
+    }
+
+    fn hash(&self, name: &str) -> u64 {
+        hash::layout_hash(name, self.grid.size(), &self.items)
+    }
+
+    /// Entries of the layout size and of the item in the hash of the layout
+    fn hash_entries(&self, name: &str, id: &str) -> u64 {
+        let (rows, cols) = self.grid.size();
+        let node_entry = self
+            .items
+            .get(id)
+            .map_or(0, |node| hash::node_entry(name, node));
+        hash::size_entry(name, rows, cols) ^ node_entry
+    }
+
+    /// Replaces the grid nested in the item by the one of `node`, reflowed to the item width
+    fn update_child(&mut self, node: &Node) -> Result<(), GridError> {
+        if let Some(item) = self.items.get_mut(&node.id) {
+            let reflowed = reflow_child(
+                node,
+                Node {
+                    w: item.w,
+                    ..node.clone()
+                },
+            )?;
+            item.child = reflowed.child;
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, id: &str) -> Result<(), GridError> {
+        match self.items.remove(id) {
+            Some(node) => node.for_cell(&mut |x, y| {
+                update_grid(&mut self.grid, &node, x, y, UpdateGridOperation::Remove)
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Separates the ids of a path to a nested grid, like "tabs/panel"
+pub const PATH_SEPARATOR: char = '/';
+
+/// Items of the grid nested in a node. It has as many columns as the node is wide, and grows
+/// in rows like any other grid.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct SubGrid {
+    pub rows: usize,
+    pub items: BTreeMap<String, Node>,
+}
+
+impl SubGrid {
+    /// Builds the nested grid as its own engine, `cols` wide. Fails if an item does not fit in
+    /// it or overlaps another one.
+    fn to_engine(&self, cols: usize) -> Result<GridEngine, GridError> {
+        let mut engine = GridEngine::new(self.rows, cols);
+        for node in self.items.values() {
+            check_bounds(engine.grid.size(), node.x, node.y, node.w, node.h)?;
+            if let Some(other_id) = engine
+                .will_collides_with(node, node.x, node.y, &engine.grid)
+                .pop()
+            {
+                return Err(GridError::Overlap {
+                    id: node.id.clone(),
+                    other_id,
+                });
+            }
+            node.for_cell(&mut |x, y| {
+                update_grid(&mut engine.grid, node, x, y, UpdateGridOperation::Add)
+            })?;
+            engine.insert_item(node);
+        }
+        Ok(engine)
+    }
+
+    /// Checks that the nested grid, and every grid nested in its items, can be built as an
+    /// engine, and that its items can be addressed by a path
+    fn validate(&self, cols: usize) -> Result<(), GridError> {
+        self.to_engine(cols)?;
+        for node in self.items.values() {
+            if node.id.contains(PATH_SEPARATOR) {
+                return Err(GridError::InvalidId {
+                    id: node.id.clone(),
+                });
+            }
+            check_size(node.w, node.h)?;
+            if let Some(child) = &node.child {
+                child.validate(node.w)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn from_engine(engine: &GridEngine) -> SubGrid {
+        SubGrid {
+            rows: engine.grid.rows(),
+            items: engine.items.clone(),
+        }
+    }
+}
+
+/// Reflows the grid nested in `node` from the width of `old_node` to its own, like `set_cols`
+/// does. When immovable items do not fit anymore, every item is packed first fit instead.
+pub(crate) fn reflow_child(old_node: &Node, node: Node) -> Result<Node, GridError> {
+    let child = match &node.child {
+        Some(child) if old_node.w != node.w => child,
+        _ => return Ok(node),
+    };
+
+    let mut engine = child.to_engine(old_node.w)?;
+    let child = match engine.set_cols(node.w) {
+        Ok(()) => SubGrid::from_engine(&engine),
+        Err(_) => {
+            let mut layout = Layout {
+                grid: Cells::new(StorageKind::Dense, child.rows.max(1), node.w),
+                items: BTreeMap::new(),
+            };
+            let mut items: Vec<&Node> = child.items.values().collect();
+            items.sort_by_key(|item| (item.y, item.x));
+            for item in items {
+                layout.insert_first_fit(item)?;
+            }
+            SubGrid {
+                rows: layout.grid.rows(),
+                items: layout.items,
+            }
+        }
+    };
+
+    Ok(Node {
+        child: Some(child),
+        ..node
+    })
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -72,14 +509,54 @@ pub struct Node {
     pub y: usize,
     pub w: usize,
     pub h: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_w: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_w: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_h: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_h: Option<usize>,
+    /// Can not be moved nor resized, neither directly nor by collisions
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub locked: bool,
+    /// Can be resized, but not moved, neither directly nor by collisions
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub no_move: bool,
+    /// Opaque payload of the consumer, like the config of the widget rendered by the node
+    #[wasm_bindgen(skip)]
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub data: Value,
+    /// Grid nested in the node, for containers like tabs or panels
+    #[wasm_bindgen(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub child: Option<SubGrid>,
+}
+
+/// Min and max size of a node, in cells. Missing values are unconstrained.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Hash, Tsify)]
+pub struct SizeConstraints {
+    #[serde(default)]
+    pub min_w: Option<usize>,
+    #[serde(default)]
+    pub max_w: Option<usize>,
+    #[serde(default)]
+    pub min_h: Option<usize>,
+    #[serde(default)]
+    pub max_h: Option<usize>,
+}
+
+/// Flags restricting how a node can be changed
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Hash, Tsify)]
+pub struct NodeFlags {
+    #[serde(default)]
+    pub locked: bool,
+    #[serde(default)]
+    pub no_move: bool,
 }
 
 #[wasm_bindgen]
 impl Node {
-    fn new(id: String, x: usize, y: usize, w: usize, h: usize) -> Node {
-        Node { id, x, y, w, h }
-    }
-
     #[wasm_bindgen(js_name = getId)]
     pub fn get_id(&self) -> String {
         self.id.clone()
@@ -93,6 +570,101 @@ impl Node {
     }
 }
 
+impl Node {
+    pub fn new(id: String, x: usize, y: usize, w: usize, h: usize) -> Node {
+        Node {
+            id,
+            x,
+            y,
+            w,
+            h,
+            min_w: None,
+            max_w: None,
+            min_h: None,
+            max_h: None,
+            locked: false,
+            no_move: false,
+            data: Value::Null,
+            child: None,
+        }
+    }
+
+    pub fn with_size_constraints(self, constraints: SizeConstraints) -> Node {
+        Node {
+            min_w: constraints.min_w,
+            max_w: constraints.max_w,
+            min_h: constraints.min_h,
+            max_h: constraints.max_h,
+            ..self
+        }
+    }
+
+    pub fn get_size_constraints(&self) -> SizeConstraints {
+        SizeConstraints {
+            min_w: self.min_w,
+            max_w: self.max_w,
+            min_h: self.min_h,
+            max_h: self.max_h,
+        }
+    }
+
+    pub fn with_flags(self, flags: NodeFlags) -> Node {
+        Node {
+            locked: flags.locked,
+            no_move: flags.no_move,
+            ..self
+        }
+    }
+
+    pub fn get_flags(&self) -> NodeFlags {
+        NodeFlags {
+            locked: self.locked,
+            no_move: self.no_move,
+        }
+    }
+
+    pub fn with_data(self, data: Value) -> Node {
+        Node { data, ..self }
+    }
+
+    pub fn get_data(&self) -> &Value {
+        &self.data
+    }
+
+    /// Makes the node a container, with an empty grid nested in it
+    pub fn with_child_grid(self) -> Node {
+        Node {
+            child: Some(SubGrid {
+                rows: self.h,
+                items: BTreeMap::new(),
+            }),
+            ..self
+        }
+    }
+
+    /// Gets a copy of the grid nested in the node as its own engine, as wide as the node.
+    /// Fails if the nested grid does not fit in the node.
+    pub fn get_child_grid(&self) -> Result<Option<GridEngine>, GridError> {
+        self.child
+            .as_ref()
+            .map(|child| child.to_engine(self.w))
+            .transpose()
+    }
+
+    /// Locked and no_move nodes stay where they are, other nodes are routed around them
+    pub fn is_immovable(&self) -> bool {
+        self.locked || self.no_move
+    }
+
+    /// Checks if both nodes share at least one cell
+    fn intersects(&self, other: &Node) -> bool {
+        self.x < other.x + other.w
+            && other.x < self.x + self.w
+            && self.y < other.y + other.h
+            && other.y < self.y + self.h
+    }
+}
+
 #[wasm_bindgen]
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash, Tsify)]
 pub struct AddChangeData {
@@ -116,16 +688,169 @@ pub struct MoveChangeData {
     pub new_value: Node,
 }
 
+#[wasm_bindgen]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash, Tsify)]
+pub struct ResizeChangeData {
+    #[wasm_bindgen(skip)]
+    pub old_value: Node,
+    #[wasm_bindgen(skip)]
+    pub new_value: Node,
+}
+
+/// Replaces the data of an item, keeping its position and size
+#[wasm_bindgen]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash, Tsify)]
+pub struct UpdateDataChangeData {
+    #[wasm_bindgen(skip)]
+    pub old_value: Node,
+    #[wasm_bindgen(skip)]
+    pub new_value: Node,
+}
+
+/// Applies `changes` to the grid nested in an item. The item keeps its position and size,
+/// `new_value` holds its nested grid once the changes are applied.
+#[wasm_bindgen]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash, Tsify)]
+pub struct NestedChangeData {
+    #[wasm_bindgen(skip)]
+    pub old_value: Node,
+    #[wasm_bindgen(skip)]
+    pub new_value: Node,
+    #[wasm_bindgen(skip)]
+    pub changes: Vec<Change>,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash, Tsify)]
+pub struct GridResizeChangeData {
+    pub old_rows: usize,
+    pub old_cols: usize,
+    pub new_rows: usize,
+    pub new_cols: usize,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash, Tsify)]
 #[serde(tag = "type", content = "value")]
 pub enum Change {
     Add(AddChangeData),
     Remove(RemoveChangeData),
     Move(MoveChangeData),
+    Resize(ResizeChangeData),
+    UpdateData(UpdateDataChangeData),
+    Nested(NestedChangeData),
+    GridResize(GridResizeChangeData),
+}
+
+impl Change {
+    /// Gets the change that reverts this one
+    pub fn inverse(&self) -> Change {
+        match self {
+            Change::Add(data) => Change::Remove(RemoveChangeData {
+                value: data.value.clone(),
+            }),
+            Change::Remove(data) => Change::Add(AddChangeData {
+                value: data.value.clone(),
+            }),
+            Change::Move(data) => Change::Move(MoveChangeData {
+                old_value: data.new_value.clone(),
+                new_value: data.old_value.clone(),
+            }),
+            Change::Resize(data) => Change::Resize(ResizeChangeData {
+                old_value: data.new_value.clone(),
+                new_value: data.old_value.clone(),
+            }),
+            Change::UpdateData(data) => Change::UpdateData(UpdateDataChangeData {
+                old_value: data.new_value.clone(),
+                new_value: data.old_value.clone(),
+            }),
+            Change::Nested(data) => Change::Nested(NestedChangeData {
+                old_value: data.new_value.clone(),
+                new_value: data.old_value.clone(),
+                changes: batch::invert(&data.changes),
+            }),
+            Change::GridResize(data) => Change::GridResize(GridResizeChangeData {
+                old_rows: data.new_rows,
+                old_cols: data.new_cols,
+                new_rows: data.old_rows,
+                new_cols: data.old_cols,
+            }),
+        }
+    }
+}
+
+/// Default number of batches kept by the undo history
+pub const DEFAULT_HISTORY_DEPTH: usize = 100;
+
+/// A batch of changes applied by an engine operation, with the layout it was applied to
+#[derive(Clone, Debug)]
+struct HistoryEntry {
+    layout: String,
+    changes: Vec<Change>,
+}
+
+/// Undo and redo stacks of the batches applied by the engine operations
+#[derive(Debug)]
+struct History {
+    undo: VecDeque<HistoryEntry>,
+    redo: Vec<HistoryEntry>,
+    depth: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            depth: DEFAULT_HISTORY_DEPTH,
+        }
+    }
+}
+
+impl History {
+    /// Records a new batch, dropping the oldest ones past the depth and everything to redo
+    fn record(&mut self, layout: &str, changes: Vec<Change>) {
+        if changes.is_empty() {
+            return;
+        }
+
+        self.undo.push_back(HistoryEntry {
+            layout: layout.to_string(),
+            changes,
+        });
+        self.truncate();
+        self.redo.clear();
+    }
+
+    fn truncate(&mut self) {
+        while self.undo.len() > self.depth {
+            self.undo.pop_front();
+        }
+    }
+}
+
+/// Result of an operation run without being applied
+#[derive(Clone)]
+pub struct Preview {
+    /// Changes the operation would apply, in the order they would be applied
+    pub changes: Vec<Change>,
+    /// The grid as it would be after the operation
+    pub grid_view: GridView,
+}
+
+/// Operations grouped to be applied as a single batch
+#[derive(Debug)]
+struct Transaction {
+    /// Working grid with every successful operation of the transaction
+    grid: Cells,
+    /// Number of pending changes made by the successful operations
+    checkpoint: usize,
 }
 
 #[derive(Debug, Eq, PartialEq, Hash, Clone, Serialize, Deserialize, Tsify)]
 pub struct BatchChangeValue {
+    /// Layout the changes were applied to
+    #[serde(default = "default_layout")]
+    pub layout: String,
     pub changes: Vec<Change>,
     pub hash_before: String,
     pub hash_after: String,
@@ -150,42 +875,25 @@ impl TryFrom<Vec<u8>> for EventValue {
     type Error = GridError;
 
     fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
-        let serialized = match String::from_utf8(value) {
-            Ok(serialized) => serialized,
-            Err(err) => {
-                return Err(GridError::new(
-                    "Error converting bytes to string",
-                    "",
-                    Some(Box::new(err)),
-                ))
-            }
-        };
-
-        let event_value: EventValue = match serde_json::from_str(&serialized) {
-            Ok(event_value) => event_value,
-            Err(err) => {
-                return Err(GridError::new(
-                    "Error deserializing EventValue",
-                    "",
-                    Some(Box::new(err)),
-                ))
-            }
-        };
+        let serialized = String::from_utf8(value)?;
+        let event_value: EventValue = serde_json::from_str(&serialized)?;
 
         Ok(event_value)
     }
 }
 
-impl Into<Vec<u8>> for EventValue {
-    fn into(self) -> Vec<u8> {
-        let serialized = serde_json::to_string(&self).expect("Failed to serialize EventValue");
+impl From<EventValue> for Vec<u8> {
+    fn from(event_value: EventValue) -> Self {
+        let serialized =
+            serde_json::to_string(&event_value).expect("Failed to serialize EventValue");
         serialized.into_bytes()
     }
 }
 
-impl Into<Vec<u8>> for &EventValue {
-    fn into(self) -> Vec<u8> {
-        let serialized = serde_json::to_string(&self).expect("Failed to serialize EventValue");
+impl From<&EventValue> for Vec<u8> {
+    fn from(event_value: &EventValue) -> Self {
+        let serialized =
+            serde_json::to_string(event_value).expect("Failed to serialize EventValue");
         serialized.into_bytes()
     }
 }
@@ -196,22 +904,76 @@ pub enum EventName {
     BatchChange,
 }
 
+/// How the nodes hit by an incoming node are relocated
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CollisionStrategy {
+    /// Collided nodes are pushed right below the incoming node
+    #[default]
+    PushDown,
+    /// Collided nodes are pushed to the free columns on the left or right of the incoming node,
+    /// falling back to `PushDown` when there is no room on either side
+    PushSide,
+    /// Collided nodes are pushed right above the incoming node,
+    /// falling back to `PushDown` when there is no room above it
+    PushUp,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(from = "GridView")]
 pub struct GridEngine {
-    pub(crate) grid: Grid<Option<String>>,
+    pub(crate) grid: Cells,
     pub(crate) items: BTreeMap<String, Node>,
+    /// Name of the active layout, the one `grid` and `items` belong to
+    #[serde(default = "default_layout", skip_serializing_if = "is_default_layout")]
+    pub(crate) layout: String,
+    /// Every other layout, by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) layouts: BTreeMap<String, Layout>,
     #[serde(skip)]
     pending_changes: Vec<Change>,
     #[serde(skip)]
+    history: History,
+    #[serde(skip)]
+    transaction: Option<Transaction>,
+    #[serde(skip)]
+    collision_strategy: CollisionStrategy,
+    #[serde(skip)]
+    swap: bool,
+    #[serde(skip)]
+    float: bool,
+    #[serde(skip)]
+    max_rows: Option<usize>,
+    /// Hash of the grid and of every layout, updated by each applied change instead of being
+    /// computed from scratch
+    #[serde(skip)]
+    hash: u64,
+    #[serde(skip)]
     pub events: EventListener<EventName, EventValue>,
 }
 
 impl GridEngine {
     pub fn new(rows: usize, cols: usize) -> GridEngine {
+        GridEngine::with_storage(rows, cols, StorageKind::default())
+    }
+
+    /// Creates an engine keeping its cells in the given storage
+    pub fn with_storage(rows: usize, cols: usize, storage: StorageKind) -> GridEngine {
+        let grid = Cells::new(storage, rows, cols);
+        let (rows, cols) = grid.size();
         GridEngine {
-            grid: Grid::new(rows, cols),
+            grid,
             items: BTreeMap::new(),
+            layout: default_layout(),
+            layouts: BTreeMap::new(),
             pending_changes: Vec::new(),
+            history: History::default(),
+            transaction: None,
+            collision_strategy: CollisionStrategy::default(),
+            swap: false,
+            float: true,
+            max_rows: None,
+            hash: hash::size_entry(DEFAULT_LAYOUT, rows, cols),
             events: EventListener::default(),
         }
     }
@@ -221,392 +983,3214 @@ impl GridEngine {
             Ok(grid_view) => grid_view,
             Err(err) => {
                 println!("Error deserializing GridView {:?}", err);
-                return Err(GridError::new(
-                    "Error deserializing GridView",
-                    "",
-                    Some(Box::new(err)),
-                ));
+                return Err(GridError::Deserialize(err));
             }
         };
 
-        return Ok(GridEngine::from(&grid_view));
+        Ok(GridEngine::from(&grid_view))
     }
 
-    fn new_node(&mut self, id: String, x: usize, y: usize, w: usize, h: usize) -> Node {
-        let node = Node::new(id, x, y, w, h);
-        node
+    pub fn get_storage_kind(&self) -> StorageKind {
+        self.grid.kind()
     }
 
-    fn create_add_change(&mut self, node: &Node) {
-        self.pending_changes.push(Change::Add(AddChangeData {
-            value: node.clone(),
-        }));
+    pub fn get_collision_strategy(&self) -> CollisionStrategy {
+        self.collision_strategy
     }
 
-    pub fn add_item(
-        &mut self,
-        id: String,
-        x: usize,
-        y: usize,
-        w: usize,
-        h: usize,
-    ) -> Result<String, GridError> {
-        if self.items.get(&id).is_some() {
-            return Err(GridError::new("Id already exists", "", None));
-        };
+    /// Sets how the nodes hit by the next add or move operations are relocated
+    pub fn set_collision_strategy(&mut self, collision_strategy: CollisionStrategy) {
+        self.collision_strategy = collision_strategy;
+    }
 
-        let node = self.new_node(id, x, y, w, h);
-        let node_id = node.id.to_string();
+    pub fn get_swap(&self) -> bool {
+        self.swap
+    }
 
-        self.handle_collision(&node, x, y, &self.grid.clone());
+    /// When swap is enabled, a moved node trades places with the node it lands exactly on, when
+    /// both have the same size. Other collisions are handled by the collision strategy.
+    /// Disabled by default.
+    pub fn set_swap(&mut self, swap: bool) {
+        self.swap = swap;
+    }
 
-        self.create_add_change(&node);
+    pub fn get_float(&self) -> bool {
+        self.float
+    }
 
-        self.apply_changes(&self.pending_changes.clone());
-        self.pending_changes.clear();
+    /// When float is disabled, every operation also floats the nodes up into the empty rows
+    /// above them, within the same batch of changes. Enabled by default.
+    pub fn set_float(&mut self, float: bool) {
+        self.float = float;
+    }
 
-        Ok(node_id)
+    pub fn get_max_rows(&self) -> Option<usize> {
+        self.max_rows
     }
 
-    fn create_remove_change(&mut self, node: &Node) {
-        self.pending_changes.push(Change::Remove(RemoveChangeData {
-            value: node.clone(),
-        }));
+    /// Limits how far the grid grows when nodes are placed or pushed past its last row.
+    /// `None`, the default, lets it grow without limit.
+    pub fn set_max_rows(&mut self, max_rows: Option<usize>) {
+        self.max_rows = max_rows;
     }
 
-    pub fn remove_item(&mut self, id: &str) -> Result<(), GridError> {
-        let node = match self.items.get(id) {
-            Some(node) => node,
-            None => Err(GridError::new("Item not found", "", None))?,
-        }
-        .clone();
+    pub fn in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
 
-        self.create_remove_change(&node);
+    /// Starts grouping the following operations, so that they are applied as a single batch on
+    /// `commit`. Operations that fail within the transaction are discarded on their own.
+    pub fn begin_transaction(&mut self) -> Result<(), GridError> {
+        self.check_no_transaction()?;
 
-        self.apply_changes(&self.pending_changes.clone());
-        self.pending_changes.clear();
+        self.transaction = Some(Transaction {
+            grid: self.grid.clone(),
+            checkpoint: 0,
+        });
         Ok(())
     }
 
-    fn will_collides_with(
-        &self,
-        node: &Node,
-        x: usize,
-        y: usize,
-        grid: &Grid<Option<String>>,
-    ) -> Vec<String> {
-        let mut collides_with = Vec::new();
-        for_cell(x, y, node.w, node.h, &mut |x, y| {
-            if let Some(cell) = grid.get(y, x) {
-                if cell.is_some() {
-                    let cell_ref = cell.as_ref().expect("Failed to get cell ref");
-                    if cell_ref != &node.id && !collides_with.contains(&cell_ref.to_string()) {
-                        collides_with.push(cell_ref.to_string());
-                    }
-                }
-            }
-            Ok(())
-        })
-        .expect("UnhandledError");
-
-        collides_with
+    /// Applies every operation of the transaction as a single batch
+    pub fn commit(&mut self) -> Result<(), GridError> {
+        match self.transaction.take() {
+            Some(_) => self.apply_pending_changes(),
+            None => Err(GridError::NoTransaction),
+        }
     }
 
-    fn handle_collision(&mut self, node: &Node, x: usize, y: usize, grid: &Grid<Option<String>>) {
-        let collides_with = self.will_collides_with(node, x, y, grid);
-        if collides_with.len() > 0 {
-            for collided_id in collides_with {
-                let collided = self
-                    .items
-                    .get(&collided_id)
-                    .expect("Failed to get collided node")
-                    .clone();
-                // Will pass here a grid in which the node is already moved
-                let mut new_grid = self.grid.clone();
-                node.for_cell(&mut |x, y| {
-                    return update_grid(&mut new_grid, node, x, y, UpdateGridOperation::Remove);
-                })
-                .expect("UnhandledError");
-
-                self.create_move_change(&collided, collided.x, y + node.h, &mut new_grid);
+    /// Discards every operation of the transaction, leaving the grid untouched
+    pub fn rollback(&mut self) -> Result<(), GridError> {
+        match self.transaction.take() {
+            Some(_) => {
+                self.pending_changes.clear();
+                Ok(())
             }
+            None => Err(GridError::NoTransaction),
         }
     }
 
-    fn create_move_change(
+    /// Runs the operations of `f` in a transaction, committing them if it succeeds and rolling
+    /// them back otherwise
+    pub fn transaction<T>(
         &mut self,
-        node: &Node,
-        new_x: usize,
-        new_y: usize,
-        grid: &Grid<Option<String>>,
-    ) {
-        let old_node = node.clone();
+        f: impl FnOnce(&mut GridEngine) -> Result<T, GridError>,
+    ) -> Result<T, GridError> {
+        self.begin_transaction()?;
 
-        self.handle_collision(node, new_x, new_y, grid);
+        match f(self) {
+            Ok(value) => {
+                self.commit()?;
+                Ok(value)
+            }
+            Err(err) => {
+                self.rollback()?;
+                Err(err)
+            }
+        }
+    }
 
-        self.pending_changes.push(Change::Move(MoveChangeData {
-            old_value: old_node,
-            new_value: Node::new(node.id.to_string(), new_x, new_y, node.w, node.h),
-        }));
+    /// Gets the changes `add_item` would make, and the resulting grid, without applying them
+    pub fn preview_add_item(
+        &self,
+        id: String,
+        x: usize,
+        y: usize,
+        w: usize,
+        h: usize,
+    ) -> Result<Preview, GridError> {
+        self.preview(|engine| engine.add_item(id, x, y, w, h).map(|_| ()))
     }
 
-    pub fn move_item(&mut self, id: &str, new_x: usize, new_y: usize) -> Result<(), GridError> {
-        let node = match self.items.get(id) {
-            Some(node) => node,
-            None => Err(GridError::new("Item not found", "", None))?,
+    /// Gets the changes `move_item` would make, such as the items it would push away, and the
+    /// resulting grid, without applying them
+    pub fn preview_move_item(&self, id: &str, x: usize, y: usize) -> Result<Preview, GridError> {
+        self.preview(|engine| engine.move_item(id, x, y))
+    }
+
+    /// Runs the operation over a copy of the engine, without listeners, within a transaction
+    /// that continues the open one, if any
+    fn preview(
+        &self,
+        operation: impl FnOnce(&mut GridEngine) -> Result<(), GridError>,
+    ) -> Result<Preview, GridError> {
+        let start = self.pending_changes.len();
+        let mut engine = GridEngine {
+            grid: self.grid.clone(),
+            items: self.items.clone(),
+            layout: self.layout.clone(),
+            layouts: self.layouts.clone(),
+            pending_changes: self.pending_changes.clone(),
+            history: History::default(),
+            transaction: Some(Transaction {
+                grid: self.get_working_grid(),
+                checkpoint: start,
+            }),
+            collision_strategy: self.collision_strategy,
+            swap: self.swap,
+            float: self.float,
+            max_rows: self.max_rows,
+            hash: self.hash,
+            events: EventListener::default(),
         };
 
-        self.create_move_change(&node.clone(), new_x, new_y, &self.grid.clone());
+        operation(&mut engine)?;
+        let changes = engine.pending_changes[start..].to_vec();
+        engine.commit()?;
 
-        self.apply_changes(&self.pending_changes.clone());
-        self.pending_changes.clear();
+        Ok(Preview {
+            changes,
+            grid_view: engine.get_grid_view(),
+        })
+    }
 
-        Ok(())
+    pub fn get_history_depth(&self) -> usize {
+        self.history.depth
     }
 
-    pub fn apply_changes(&mut self, changes: &Vec<Change>) {
-        let hash_before = self.get_grid_view().hash();
-        for change in changes.iter() {
-            match &change {
-                Change::Add(data) => {
-                    let node = &data.value;
+    /// Sets how many batches can be undone, dropping the oldest ones past it
+    pub fn set_history_depth(&mut self, depth: usize) {
+        self.history.depth = depth;
+        self.history.truncate();
+    }
 
-                    node.for_cell(&mut |x, y| {
-                        return update_grid(&mut self.grid, node, x, y, UpdateGridOperation::Add);
-                    })
-                    .expect("UnhandledError");
+    pub fn can_undo(&self) -> bool {
+        !self.history.undo.is_empty()
+    }
 
-                    self.items.insert(node.id.to_string(), node.clone());
-                }
-                Change::Remove(data) => {
-                    let node = &data.value;
+    pub fn can_redo(&self) -> bool {
+        !self.history.redo.is_empty()
+    }
 
-                    node.for_cell(&mut |x, y| {
-                        return update_grid(
-                            &mut self.grid,
-                            node,
-                            x,
-                            y,
-                            UpdateGridOperation::Remove,
-                        );
-                    })
-                    .expect("UnhandledError");
+    /// Reverts the last batch applied by an engine operation, applying its inverse changes as a
+    /// new batch. Batches received through `apply_changes` are not recorded.
+    /// Returns false when there is nothing to undo.
+    pub fn undo(&mut self) -> Result<bool, GridError> {
+        self.check_no_transaction()?;
+        let entry = match self.history.undo.pop_back() {
+            Some(entry) => entry,
+            None => return Ok(false),
+        };
 
-                    self.items.remove(&node.id);
-                }
-                Change::Move(data) => {
-                    let node = &data.new_value;
-                    let old_node = &data.old_value;
-
-                    old_node
-                        .for_cell(&mut |x, y| {
-                            return update_grid(
-                                &mut self.grid,
-                                old_node,
-                                x,
-                                y,
-                                UpdateGridOperation::Remove,
-                            );
-                        })
-                        .expect("UnhandledError");
-
-                    self.items.insert(node.id.to_string(), node.clone());
-                    node.for_cell(&mut |x, y| {
-                        return update_grid(&mut self.grid, node, x, y, UpdateGridOperation::Add);
-                    })
-                    .expect("UnhandledError");
-                }
-            }
+        let inverse = batch::invert(&entry.changes);
+        if let Err(err) = self.apply_layout_changes(&entry.layout, &inverse) {
+            self.history.undo.push_back(entry);
+            return Err(err);
         }
-        let grid_view = GridView::new(self);
-
-        self.events.trigger_event(
-            &grid_view,
-            EventName::BatchChange,
-            EventValue::BatchChange(BatchChangeValue {
-                changes: changes.iter().map(|change| change.clone()).collect(),
-                hash_before,
-                hash_after: grid_view.hash(),
-            }),
-        );
-    }
 
-    pub fn get_grid_view(&self) -> GridView {
-        GridView::new(self)
+        self.history.redo.push(entry);
+        Ok(true)
     }
-}
-
-impl TryFrom<&Vec<u8>> for GridEngine {
-    type Error = GridError;
 
-    fn try_from(bytes: &Vec<u8>) -> Result<Self, Self::Error> {
-        let serialized = match String::from_utf8(bytes.clone()) {
-            Ok(serialized) => serialized,
-            Err(err) => {
-                return Err(GridError::new(
-                    "Error converting bytes to string",
-                    "",
-                    Some(Box::new(err)),
-                ))
-            }
+    /// Replays the last undone batch. Returns false when there is nothing to redo.
+    pub fn redo(&mut self) -> Result<bool, GridError> {
+        self.check_no_transaction()?;
+        let entry = match self.history.redo.pop() {
+            Some(entry) => entry,
+            None => return Ok(false),
         };
 
-        let grid = match GridEngine::from_str(&serialized) {
-            Ok(grid) => grid,
-            Err(err) => return Err(err),
-        };
+        if let Err(err) = self.apply_layout_changes(&entry.layout, &entry.changes) {
+            self.history.redo.push(entry);
+            return Err(err);
+        }
 
-        Ok(grid)
+        self.history.undo.push_back(entry);
+        self.history.truncate();
+        Ok(true)
     }
-}
 
-impl Into<Vec<u8>> for &GridEngine {
-    fn into(self) -> Vec<u8> {
-        let serialized = serde_json::to_string(&self).expect("Failed to serialize GridEngine");
-        serialized.into_bytes()
+    /// Name of the active layout, the one every operation works on
+    pub fn get_layout(&self) -> &str {
+        &self.layout
     }
-}
 
-impl From<&GridView> for GridEngine {
-    fn from(grid_view: &GridView) -> Self {
-        GridEngine {
-            grid: grid_view.grid.clone(),
-            items: grid_view.items.clone(),
-            pending_changes: Vec::new(),
-            events: EventListener::default(),
+    /// Names of every layout, including the active one, sorted
+    pub fn get_layout_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.layouts.keys().cloned().collect();
+        names.push(self.layout.clone());
+        names.sort();
+        names
+    }
+
+    /// Stores a new layout with `cols` columns, holding the same items as the active one,
+    /// reflowed as `set_cols` does. It does not become the active layout.
+    pub fn add_layout(&mut self, name: &str, cols: usize) -> Result<(), GridError> {
+        if self.layout == name || self.layouts.contains_key(name) {
+            return Err(GridError::DuplicateLayout {
+                name: name.to_string(),
+            });
         }
+        self.check_no_transaction()?;
+
+        let mut engine = GridEngine::new(0, 0);
+        engine.grid = self.grid.clone();
+        engine.items = self.items.clone();
+        engine.hash = hash::layout_hash(&engine.layout, engine.grid.size(), &engine.items);
+        engine.float = self.float;
+        engine.max_rows = self.max_rows;
+        engine.set_cols(cols)?;
+
+        let layout = Layout {
+            grid: engine.grid,
+            items: engine.items,
+        };
+        self.hash ^= layout.hash(name);
+        self.layouts.insert(name.to_string(), layout);
+        Ok(())
     }
-}
 
-mod tests {
-    use super::*;
+    /// Makes the stored layout the active one, storing the previously active layout
+    pub fn set_layout(&mut self, name: &str) -> Result<(), GridError> {
+        if self.layout == name {
+            return Ok(());
+        }
+        self.check_no_transaction()?;
 
-    #[test]
-    fn test_for_cell() {
-        let mut results = Vec::new();
-        let mut callback = |x: usize, y: usize| {
-            results.push((x, y));
-            Ok(())
+        let layout = match self.layouts.remove(name) {
+            Some(layout) => layout,
+            None => Err(GridError::LayoutNotFound {
+                name: name.to_string(),
+            })?,
         };
 
-        for_cell(1, 2, 2, 2, &mut callback).unwrap();
+        let previous = Layout {
+            grid: std::mem::replace(&mut self.grid, layout.grid),
+            items: std::mem::replace(&mut self.items, layout.items),
+        };
+        let previous_name = std::mem::replace(&mut self.layout, name.to_string());
+        self.layouts.insert(previous_name, previous);
+        Ok(())
+    }
 
-        assert_eq!(results, vec![(1, 2), (1, 3), (2, 2), (2, 3)]);
+    /// Removes a stored layout. The active layout can not be removed.
+    pub fn remove_layout(&mut self, name: &str) -> Result<(), GridError> {
+        if self.layout == name {
+            return Err(GridError::ActiveLayout {
+                name: name.to_string(),
+            });
+        }
+
+        match self.layouts.remove(name) {
+            Some(layout) => {
+                self.hash ^= layout.hash(name);
+                Ok(())
+            }
+            None => Err(GridError::LayoutNotFound {
+                name: name.to_string(),
+            }),
+        }
     }
 
-    #[test]
-    fn test_add_item() {
-        let mut engine = GridEngine::new(10, 10);
-        let item_0_id = engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+    /// Applies changes targeting the given layout, such as the ones of a `BatchChange` event,
+    /// keeping the active layout as it is
+    pub fn apply_layout_changes(
+        &mut self,
+        layout: &str,
+        changes: &[Change],
+    ) -> Result<(), GridError> {
+        let active = self.layout.clone();
+        self.set_layout(layout)?;
+        let result = self.apply_changes(changes);
+        self.set_layout(&active)?;
+        result
+    }
 
-        assert!(engine.items.len() == 1);
-        for_cell(0, 0, 2, 2, &mut |x, y| {
-            assert_eq!(engine.grid.get(y, x).unwrap().as_ref().unwrap(), &item_0_id);
-            Ok(())
-        })
-        .unwrap();
+    fn new_node(&mut self, id: String, x: usize, y: usize, w: usize, h: usize) -> Node {
+        Node::new(id, x, y, w, h)
     }
 
-    #[test]
-    fn test_add_item_handle_duplicated_id() {
-        let mut engine = GridEngine::new(10, 10);
-        let item_0_id = engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+    fn create_add_change(&mut self, node: &Node) {
+        self.pending_changes.push(Change::Add(AddChangeData {
+            value: node.clone(),
+        }));
+    }
 
-        assert!(engine.add_item("0".to_string(), 0, 0, 2, 2).is_err())
+    pub fn add_item(
+        &mut self,
+        id: String,
+        x: usize,
+        y: usize,
+        w: usize,
+        h: usize,
+    ) -> Result<String, GridError> {
+        let node = self.new_node(id, x, y, w, h);
+        self.add_node(node)
     }
 
-    #[test]
-    fn test_add_item_handle_collision() {
-        let mut engine = GridEngine::new(10, 10);
-        let item_0_id = engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
-        let item_1_id = engine.add_item("1".to_string(), 0, 0, 2, 2).unwrap();
+    /// Adds a node built by the caller, keeping options like its size constraints
+    pub fn add_node(&mut self, node: Node) -> Result<String, GridError> {
+        if self.get_pending_node(&node.id).is_some() {
+            return Err(GridError::DuplicateId { id: node.id });
+        };
 
-        // Item 0 should stay in position 0, 0
-        let item_0 = engine.items.get(&item_0_id).unwrap();
-        assert_eq!(item_0.x, 0);
-        assert_eq!(item_0.y, 2);
-        item_0
-            .for_cell(&mut |x, y| {
-                assert_eq!(engine.grid.get(y, x).unwrap().as_ref().unwrap(), &item_0_id);
-                Ok(())
-            })
-            .unwrap();
+        check_size(node.w, node.h)?;
+        check_constraints(&node)?;
 
-        // Item 1 should go to position 0, 2
-        let item_1 = engine.items.get(&item_1_id).unwrap();
-        assert_eq!(item_1.x, 0);
-        assert_eq!(item_1.y, 0);
-        item_1
-            .for_cell(&mut |x, y| {
-                assert_eq!(engine.grid.get(y, x).unwrap().as_ref().unwrap(), &item_1_id);
-                Ok(())
-            })
-            .unwrap();
+        let node_id = node.id.to_string();
+
+        let mut grid = self.get_working_grid();
+        let result = self
+            .handle_collision(&node, node.x, node.y, &mut grid)
+            .and_then(|_| {
+                self.create_add_change(&node);
+                self.handle_float(&mut grid)
+            });
+        self.finish_pending_changes(result, grid)?;
+
+        Ok(node_id)
     }
 
-    #[test]
-    fn test_remove_item() {
-        let mut engine = GridEngine::new(10, 10);
-        let item_0_id = engine.add_item("0".to_string(), 0, 0, 2, 3).unwrap();
-        engine.remove_item(&item_0_id).unwrap();
-        for_cell(0, 0, 2, 3, &mut |x, y| {
-            let value = engine.grid.get(y, x).unwrap();
-            assert_eq!(value, &None);
-            Ok(())
-        })
-        .unwrap();
+    /// Adds the item at the first free top-left position, growing the grid if none fits
+    pub fn add_item_auto(&mut self, id: String, w: usize, h: usize) -> Result<String, GridError> {
+        let node = self.new_node(id, 0, 0, w, h);
+        self.add_node_auto(node)
     }
 
-    #[test]
-    fn test_move_item() {
-        let mut engine = GridEngine::new(10, 10);
-        let item_0_id = engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
-        engine.move_item(&item_0_id, 1, 1).unwrap();
+    /// Adds a node built by the caller at the first free top-left position, ignoring its x and y
+    pub fn add_node_auto(&mut self, node: Node) -> Result<String, GridError> {
+        check_size(node.w, node.h)?;
+        let grid = self.get_working_grid();
+        let (x, y) = match self.find_free_position(&node, &grid) {
+            Some(position) => position,
+            None => {
+                return Err(GridError::OutOfBounds(OutOfBoundsError {
+                    x: 0,
+                    y: 0,
+                    w: node.w,
+                    h: node.h,
+                    rows: grid.rows(),
+                    cols: grid.cols(),
+                }));
+            }
+        };
 
-        // Asserts that its present on the new position
-        for_cell(1, 1, 2, 2, &mut |x, y| {
-            let item_on_expected_position = engine.grid.get(y, x).unwrap().as_ref().unwrap();
-            assert_eq!(item_on_expected_position, &item_0_id);
-            Ok(())
-        })
-        .unwrap();
+        self.add_node(Node { x, y, ..node })
+    }
 
-        // Asserts that its not present on the old position
-        for_cell(0, 0, 1, 1, &mut |x, y| {
-            assert_eq!(engine.grid.get(y, x).unwrap(), &None);
-            Ok(())
-        })
-        .unwrap();
+    /// Gets every top-left position, in reading order, where a `w`x`h` item fits without
+    /// colliding with other items
+    pub fn get_free_positions(&self, w: usize, h: usize) -> Vec<(usize, usize)> {
+        let probe = Node::new(String::new(), 0, 0, w, h);
+        let grid = self.working_grid();
+        let (rows, cols) = grid.size();
+        if w == 0 || h == 0 || w > cols || h > rows {
+            return Vec::new();
+        }
+
+        (0..=rows - h)
+            .flat_map(|y| (0..=cols - w).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.will_collides_with(&probe, x, y, grid).is_empty())
+            .collect()
     }
 
-    #[test]
-    fn test_move_item_handle_collision() {
-        let mut engine = GridEngine::new(10, 10);
-        let item_0_id = engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
-        let item_1_id = engine.add_item("1".to_string(), 0, 2, 2, 2).unwrap();
-        engine.move_item("0", 0, 1).unwrap();
+    /// Whether a `w`x`h` item can be placed somewhere without pushing other items
+    pub fn can_fit(&self, w: usize, h: usize) -> bool {
+        free_space::can_fit(self.working_grid(), w, h)
+    }
 
-        // Item 0 should go to position 0, 1
-        let item_0 = engine.items.get(&item_0_id).unwrap();
-        assert_eq!(item_0.x, 0);
-        assert_eq!(item_0.y, 1);
-        item_0
-            .for_cell(&mut |x, y| {
+    /// Gets the first position, in reading order, where the node fits without collisions.
+    /// Positions going past the last row are also considered, as the grid can grow.
+    fn find_free_position(&self, node: &Node, grid: &Cells) -> Option<(usize, usize)> {
+        let (rows, cols) = grid.size();
+        if node.w == 0 || node.w > cols {
+            return None;
+        }
+
+        (0..=rows)
+            .flat_map(|y| (0..=cols - node.w).map(move |x| (x, y)))
+            .find(|&(x, y)| self.will_collides_with(node, x, y, grid).is_empty())
+    }
+
+    fn create_remove_change(&mut self, node: &Node) {
+        self.pending_changes.push(Change::Remove(RemoveChangeData {
+            value: node.clone(),
+        }));
+    }
+
+    pub fn remove_item(&mut self, id: &str) -> Result<(), GridError> {
+        let node = match self.get_pending_node(id) {
+            Some(node) => node,
+            None => Err(GridError::ItemNotFound { id: id.to_string() })?,
+        };
+
+        self.create_remove_change(&node);
+
+        let mut grid = self.get_working_grid();
+        let result = node
+            .for_cell(&mut |x, y| update_grid(&mut grid, &node, x, y, UpdateGridOperation::Remove))
+            .and_then(|_| self.handle_float(&mut grid));
+        self.finish_pending_changes(result, grid)
+    }
+
+    /// Changes the number of columns of the active layout and reflows its nodes, as a single
+    /// batch of changes.
+    /// The x and w of every node are scaled to the new columns, then, in reading order, each
+    /// node is placed on the first free row from its own one down. Immovable nodes are placed
+    /// first and keep their position.
+    pub fn set_cols(&mut self, cols: usize) -> Result<(), GridError> {
+        if cols == 0 {
+            return Err(GridError::InvalidSize {
+                w: cols,
+                h: self.grid.rows(),
+            });
+        }
+
+        self.check_no_transaction()?;
+
+        let (old_rows, old_cols) = self.grid.size();
+        if cols == old_cols {
+            return Ok(());
+        }
+
+        let mut nodes: Vec<Node> = self.items.values().cloned().collect();
+        nodes.sort_by_key(|node| (!node.is_immovable(), node.y, node.x));
+
+        let mut grid = Cells::new(self.grid.kind(), old_rows, cols);
+        let mut updates = Vec::new();
+        for node in nodes {
+            let mut new_node = reflow_child(&node, scale_node(&node, old_cols, cols))?;
+            check_constraints(&new_node)?;
+
+            if !node.is_immovable() {
+                new_node.y = (node.y..)
+                    .find(|&y| {
+                        self.will_collides_with(&new_node, new_node.x, y, &grid)
+                            .is_empty()
+                    })
+                    .expect("Rows past the grid are always free");
+            } else if let Some(collided) = self
+                .will_collides_with(&new_node, new_node.x, new_node.y, &grid)
+                .first()
+            {
+                return Err(GridError::Collision {
+                    id: new_node.id.clone(),
+                    obstacle_id: collided.clone(),
+                });
+            }
+
+            let bottom = new_node.y + new_node.h;
+            if bottom > grid.rows() && self.max_rows.is_none_or(|max_rows| bottom <= max_rows) {
+                grid.resize(bottom, cols);
+            }
+            check_bounds(grid.size(), new_node.x, new_node.y, new_node.w, new_node.h)?;
+            new_node.for_cell(&mut |x, y| {
+                update_grid(&mut grid, &new_node, x, y, UpdateGridOperation::Add)
+            })?;
+
+            if new_node != node {
+                updates.push((node, new_node));
+            }
+        }
+
+        // The grid only loses columns once every node has left them
+        let rows = grid.rows();
+        if rows != old_rows || cols > old_cols {
+            self.pending_changes
+                .push(Change::GridResize(GridResizeChangeData {
+                    old_rows,
+                    old_cols,
+                    new_rows: rows,
+                    new_cols: cols.max(old_cols),
+                }));
+        }
+        for (old_node, new_node) in updates {
+            if old_node.w == new_node.w && old_node.h == new_node.h {
+                self.pending_changes.push(Change::Move(MoveChangeData {
+                    old_value: old_node,
+                    new_value: new_node,
+                }));
+            } else {
+                self.pending_changes.push(Change::Resize(ResizeChangeData {
+                    old_value: old_node,
+                    new_value: new_node,
+                }));
+            }
+        }
+        if cols < old_cols {
+            self.pending_changes
+                .push(Change::GridResize(GridResizeChangeData {
+                    old_rows: rows,
+                    old_cols,
+                    new_rows: rows,
+                    new_cols: cols,
+                }));
+        }
+
+        let result = self.handle_float(&mut grid);
+        self.finish_pending_changes(result, grid)
+    }
+
+    /// Floats every node up into the empty rows above it, as a single batch of changes
+    pub fn compact(&mut self) -> Result<(), GridError> {
+        let mut grid = self.get_working_grid();
+        let result = self.create_compact_changes(&mut grid);
+        self.finish_pending_changes(result, grid)
+    }
+
+    fn will_collides_with(&self, node: &Node, x: usize, y: usize, grid: &Cells) -> Vec<String> {
+        grid.collisions(x, y, node.w, node.h, &node.id)
+    }
+
+    /// Gets the node as it is after the pending changes, falling back to the applied one
+    fn get_pending_node(&self, id: &str) -> Option<Node> {
+        let pending = self
+            .pending_changes
+            .iter()
+            .rev()
+            .find_map(|change| match change {
+                Change::Add(data) if data.value.id == id => Some(Some(data.value.clone())),
+                Change::Remove(data) if data.value.id == id => Some(None),
+                Change::Move(data) if data.new_value.id == id => Some(Some(data.new_value.clone())),
+                Change::Resize(data) if data.new_value.id == id => {
+                    Some(Some(data.new_value.clone()))
+                }
+                Change::UpdateData(data) if data.new_value.id == id => {
+                    Some(Some(data.new_value.clone()))
+                }
+                Change::Nested(data) if data.new_value.id == id => {
+                    Some(Some(data.new_value.clone()))
+                }
+                _ => None,
+            });
+
+        match pending {
+            Some(node) => node,
+            None => self.items.get(id).cloned(),
+        }
+    }
+
+    /// Gets all the nodes as they are after the pending changes
+    fn get_pending_nodes(&self) -> Vec<Node> {
+        let mut nodes = self.items.clone();
+        for change in self.pending_changes.iter() {
+            match change {
+                Change::Add(data) => {
+                    nodes.insert(data.value.id.clone(), data.value.clone());
+                }
+                Change::Remove(data) => {
+                    nodes.remove(&data.value.id);
+                }
+                Change::Move(data) => {
+                    nodes.insert(data.new_value.id.clone(), data.new_value.clone());
+                }
+                Change::Resize(data) => {
+                    nodes.insert(data.new_value.id.clone(), data.new_value.clone());
+                }
+                Change::UpdateData(data) => {
+                    nodes.insert(data.new_value.id.clone(), data.new_value.clone());
+                }
+                Change::Nested(data) => {
+                    nodes.insert(data.new_value.id.clone(), data.new_value.clone());
+                }
+                Change::GridResize(_) => {}
+            }
+        }
+        nodes.into_values().collect()
+    }
+
+    fn handle_float(&mut self, grid: &mut Cells) -> Result<(), GridError> {
+        if self.float {
+            return Ok(());
+        }
+        self.create_compact_changes(grid)
+    }
+
+    /// Moves each node, from the top one to the bottom one, up to the highest free row above it
+    fn create_compact_changes(&mut self, grid: &mut Cells) -> Result<(), GridError> {
+        let mut nodes = self.get_pending_nodes();
+        nodes.sort_by_key(|node| (node.y, node.x));
+
+        for node in nodes.into_iter().filter(|node| !node.is_immovable()) {
+            let mut new_y = node.y;
+            while new_y > 0
+                && self
+                    .will_collides_with(&node, node.x, new_y - 1, grid)
+                    .is_empty()
+            {
+                new_y -= 1;
+            }
+
+            if new_y != node.y {
+                self.create_move_change(&node, node.x, new_y, grid)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Places `node` at (x, y) on the working `grid` and relocates every node it collides with
+    fn handle_collision(
+        &mut self,
+        node: &Node,
+        x: usize,
+        y: usize,
+        grid: &mut Cells,
+    ) -> Result<(), GridError> {
+        if let Some(bottom) = y.checked_add(node.h) {
+            self.grow_rows(grid, bottom);
+        }
+        check_bounds(grid.size(), x, y, node.w, node.h)?;
+
+        let collides_with = self.will_collides_with(node, x, y, grid);
+
+        // Claims the target area first, so no collided node is relocated into it
+        let placed = Node {
+            x,
+            y,
+            ..node.clone()
+        };
+        placed.for_cell(&mut |x, y| update_grid(grid, &placed, x, y, UpdateGridOperation::Add))?;
+
+        for collided_id in collides_with {
+            let collided = match self.get_pending_node(&collided_id) {
+                Some(collided) => collided,
+                None => Err(GridError::Internal {
+                    description: format!("Collided item {} not found", collided_id),
+                })?,
+            };
+
+            // It may already have been pushed away by a previously relocated node
+            if !collided.intersects(&placed) {
+                continue;
+            }
+
+            if collided.is_immovable() {
+                return Err(GridError::Collision {
+                    id: placed.id.clone(),
+                    obstacle_id: collided.id.clone(),
+                });
+            }
+
+            let (new_x, new_y) = self.get_collision_target(&placed, &collided, grid);
+            self.create_move_change(&collided, new_x, new_y, grid)?;
+        }
+
+        Ok(())
+    }
+
+    /// Grows the working `grid` up to `rows`, if `max_rows` allows it, and records it as a pending
+    /// change. Every growth within the same batch is merged into the first one, which comes before
+    /// any change that relies on the new rows.
+    fn grow_rows(&mut self, grid: &mut Cells, rows: usize) {
+        if rows <= grid.rows() || self.max_rows.is_some_and(|max_rows| rows > max_rows) {
+            return;
+        }
+
+        let (old_rows, cols) = grid.size();
+        grid.resize(rows, cols);
+
+        let pending_resize = self
+            .pending_changes
+            .iter_mut()
+            .find_map(|change| match change {
+                Change::GridResize(data) => Some(data),
+                _ => None,
+            });
+
+        match pending_resize {
+            Some(data) => data.new_rows = rows,
+            None => self
+                .pending_changes
+                .push(Change::GridResize(GridResizeChangeData {
+                    old_rows,
+                    old_cols: cols,
+                    new_rows: rows,
+                    new_cols: cols,
+                })),
+        }
+    }
+
+    /// Gets where the `collided` node goes when `placed` lands over it
+    fn get_collision_target(&self, placed: &Node, collided: &Node, grid: &Cells) -> (usize, usize) {
+        let push_down = (
+            collided.x,
+            self.get_y_below_immovables(collided, collided.x, placed.y + placed.h, grid),
+        );
+
+        match self.collision_strategy {
+            CollisionStrategy::PushDown => push_down,
+            CollisionStrategy::PushSide => self
+                .get_side_target(placed, collided, grid)
+                .map(|x| (x, collided.y))
+                .unwrap_or(push_down),
+            CollisionStrategy::PushUp => placed
+                .y
+                .checked_sub(collided.h)
+                .filter(|&y| {
+                    self.will_collides_with(collided, collided.x, y, grid)
+                        .is_empty()
+                })
+                .map(|y| (collided.x, y))
+                .unwrap_or(push_down),
+        }
+    }
+
+    /// Gets the first row, from `y` down, where the node does not land over an immovable node
+    fn get_y_below_immovables(&self, node: &Node, x: usize, mut y: usize, grid: &Cells) -> usize {
+        loop {
+            let immovables_bottom = self
+                .will_collides_with(node, x, y, grid)
+                .iter()
+                .filter_map(|id| self.get_pending_node(id))
+                .filter(|collided| collided.is_immovable())
+                .map(|collided| collided.y + collided.h)
+                .max();
+
+            match immovables_bottom {
+                Some(bottom) => y = bottom,
+                None => return y,
+            }
+        }
+    }
+
+    /// Gets a free column for `collided` at its own row, next to `placed`.
+    /// The side where the collided node mostly is gets tried first.
+    fn get_side_target(&self, placed: &Node, collided: &Node, grid: &Cells) -> Option<usize> {
+        let left = placed.x.checked_sub(collided.w);
+        let right = Some(placed.x + placed.w).filter(|x| x + collided.w <= grid.cols());
+
+        let sides = if collided.x * 2 + collided.w < placed.x * 2 + placed.w {
+            [left, right]
+        } else {
+            [right, left]
+        };
+
+        sides.into_iter().flatten().find(|&x| {
+            self.will_collides_with(collided, x, collided.y, grid)
+                .is_empty()
+        })
+    }
+
+    fn create_move_change(
+        &mut self,
+        node: &Node,
+        new_x: usize,
+        new_y: usize,
+        grid: &mut Cells,
+    ) -> Result<(), GridError> {
+        let new_node = Node {
+            x: new_x,
+            y: new_y,
+            ..node.clone()
+        };
+        self.create_update_change(node, new_node, grid)
+    }
+
+    /// Replaces `node` by `new_node`, pushing away what it lands over.
+    /// Emits a `Resize` change when the size changes, a `Move` otherwise.
+    fn create_update_change(
+        &mut self,
+        node: &Node,
+        new_node: Node,
+        grid: &mut Cells,
+    ) -> Result<(), GridError> {
+        let old_node = node.clone();
+
+        node.for_cell(&mut |x, y| update_grid(grid, node, x, y, UpdateGridOperation::Remove))?;
+
+        self.handle_collision(&new_node, new_node.x, new_node.y, grid)?;
+
+        if old_node.w == new_node.w && old_node.h == new_node.h {
+            self.pending_changes.push(Change::Move(MoveChangeData {
+                old_value: old_node,
+                new_value: new_node,
+            }));
+        } else {
+            self.pending_changes.push(Change::Resize(ResizeChangeData {
+                old_value: old_node,
+                new_value: new_node,
+            }));
+        }
+
+        Ok(())
+    }
+
+    pub fn move_item(&mut self, id: &str, new_x: usize, new_y: usize) -> Result<(), GridError> {
+        let node = match self.get_pending_node(id) {
+            Some(node) => node,
+            None => Err(GridError::ItemNotFound { id: id.to_string() })?,
+        };
+        check_updatable(&node, new_x, new_y, node.w, node.h)?;
+
+        let mut grid = self.get_working_grid();
+        let result = match self.get_swap_target(&node, new_x, new_y, &grid) {
+            Some(other) => self.create_swap_changes(&node, &other, &mut grid),
+            None => self.create_move_change(&node, new_x, new_y, &mut grid),
+        }
+        .and_then(|_| self.handle_float(&mut grid));
+        self.finish_pending_changes(result, grid)
+    }
+
+    /// Gets the node that `node` would trade places with when moved to (x, y), which is the
+    /// only node there, at exactly that position and of the same size. Always `None` unless swap
+    /// is enabled.
+    fn get_swap_target(&self, node: &Node, x: usize, y: usize, grid: &Cells) -> Option<Node> {
+        if !self.swap {
+            return None;
+        }
+
+        let [other_id] = self.will_collides_with(node, x, y, grid).try_into().ok()?;
+        self.get_pending_node(&other_id).filter(|other| {
+            other.x == x
+                && other.y == y
+                && other.w == node.w
+                && other.h == node.h
+                && !other.is_immovable()
+        })
+    }
+
+    /// Exchanges the positions of two nodes of the same size, as two `Move` changes
+    fn create_swap_changes(
+        &mut self,
+        node: &Node,
+        other: &Node,
+        grid: &mut Cells,
+    ) -> Result<(), GridError> {
+        let moved = Node {
+            x: other.x,
+            y: other.y,
+            ..node.clone()
+        };
+        let swapped = Node {
+            x: node.x,
+            y: node.y,
+            ..other.clone()
+        };
+
+        for old_node in [node, other] {
+            old_node.for_cell(&mut |x, y| {
+                update_grid(grid, old_node, x, y, UpdateGridOperation::Remove)
+            })?;
+        }
+        for new_node in [&moved, &swapped] {
+            new_node.for_cell(&mut |x, y| {
+                update_grid(grid, new_node, x, y, UpdateGridOperation::Add)
+            })?;
+        }
+
+        self.pending_changes.push(Change::Move(MoveChangeData {
+            old_value: node.clone(),
+            new_value: moved,
+        }));
+        self.pending_changes.push(Change::Move(MoveChangeData {
+            old_value: other.clone(),
+            new_value: swapped,
+        }));
+
+        Ok(())
+    }
+
+    pub fn resize_item(&mut self, id: &str, new_w: usize, new_h: usize) -> Result<(), GridError> {
+        let (x, y) = match self.get_pending_node(id) {
+            Some(node) => (node.x, node.y),
+            None => Err(GridError::ItemNotFound { id: id.to_string() })?,
+        };
+
+        self.update_item(id, x, y, new_w, new_h)
+    }
+
+    /// Moves and resizes the item at once, as a single `Resize` change
+    pub fn update_item(
+        &mut self,
+        id: &str,
+        new_x: usize,
+        new_y: usize,
+        new_w: usize,
+        new_h: usize,
+    ) -> Result<(), GridError> {
+        let node = match self.get_pending_node(id) {
+            Some(node) => node,
+            None => Err(GridError::ItemNotFound { id: id.to_string() })?,
+        };
+        check_size(new_w, new_h)?;
+        check_updatable(&node, new_x, new_y, new_w, new_h)?;
+
+        let new_node = Node {
+            x: new_x,
+            y: new_y,
+            w: new_w,
+            h: new_h,
+            ..node.clone()
+        };
+        check_constraints(&new_node)?;
+        let new_node = reflow_child(&node, new_node)?;
+
+        let mut grid = self.get_working_grid();
+        let result = self
+            .create_update_change(&node, new_node, &mut grid)
+            .and_then(|_| self.handle_float(&mut grid));
+        self.finish_pending_changes(result, grid)
+    }
+
+    /// Replaces the data of the item, in every layout, without touching its position or size
+    pub fn update_item_data(&mut self, id: &str, data: Value) -> Result<(), GridError> {
+        let node = match self.get_pending_node(id) {
+            Some(node) => node,
+            None => Err(GridError::ItemNotFound { id: id.to_string() })?,
+        };
+        if node.data == data {
+            return Ok(());
+        }
+
+        let grid = self.get_working_grid();
+        self.pending_changes
+            .push(Change::UpdateData(UpdateDataChangeData {
+                new_value: Node {
+                    data,
+                    ..node.clone()
+                },
+                old_value: node,
+            }));
+        self.finish_pending_changes(Ok(()), grid)
+    }
+
+    /// Gets a copy of the grid nested in the item at the path, like "tabs/panel", as its own
+    /// engine, `None` if there is no such grid
+    pub fn get_child(&self, path: &str) -> Result<Option<GridEngine>, GridError> {
+        let (id, rest) = split_path(path);
+        let child = match self.items.get(id) {
+            Some(node) => node.get_child_grid()?,
+            None => None,
+        };
+        match (child, rest) {
+            (Some(child), Some(rest)) => child.get_child(rest),
+            (child, _) => Ok(child),
+        }
+    }
+
+    /// Runs the operations of `operation` in a transaction on the grid nested in the item at
+    /// the path. They are applied as a single `Nested` change of this engine, so nested
+    /// operations trigger a single batch on the root engine.
+    pub fn update_child(
+        &mut self,
+        path: &str,
+        operation: impl FnOnce(&mut GridEngine) -> Result<(), GridError>,
+    ) -> Result<(), GridError> {
+        let grid = self.get_working_grid();
+        let result = self.create_nested_change(path, operation);
+        self.finish_pending_changes(result, grid)
+    }
+
+    /// Moves the item from this grid into the grid nested in the item at the path. Locked and
+    /// no_move items can not change grids.
+    pub fn move_into_child(
+        &mut self,
+        id: &str,
+        path: &str,
+        x: usize,
+        y: usize,
+    ) -> Result<(), GridError> {
+        let node = match self.get_pending_node(id) {
+            Some(node) => node,
+            None => Err(GridError::ItemNotFound { id: id.to_string() })?,
+        };
+        check_movable(&node)?;
+
+        // Once removed, the item can not be found on the path anymore, so it can not be moved
+        // into itself
+        self.create_remove_change(&node);
+
+        let mut grid = self.get_working_grid();
+        let moved = Node {
+            x,
+            y,
+            ..node.clone()
+        };
+        let result = node
+            .for_cell(&mut |x, y| update_grid(&mut grid, &node, x, y, UpdateGridOperation::Remove))
+            .and_then(|_| {
+                self.create_nested_change(path, |child| child.add_node(moved).map(|_| ()))
+            })
+            .and_then(|_| self.handle_float(&mut grid));
+        self.finish_pending_changes(result, grid)
+    }
+
+    /// Moves the item out of the grid nested in the item at the path, into this grid
+    pub fn move_out_of_child(
+        &mut self,
+        path: &str,
+        id: &str,
+        x: usize,
+        y: usize,
+    ) -> Result<(), GridError> {
+        if self.get_pending_node(id).is_some() {
+            return Err(GridError::DuplicateId { id: id.to_string() });
+        }
+
+        let mut grid = self.get_working_grid();
+        let mut moved = None;
+        let result = self
+            .create_nested_change(path, |child| {
+                let node = match child.get_pending_node(id) {
+                    Some(node) => node,
+                    None => Err(GridError::ItemNotFound { id: id.to_string() })?,
+                };
+                check_movable(&node)?;
+                child.remove_item(id)?;
+                moved = Some(Node { x, y, ..node });
+                Ok(())
+            })
+            .and_then(|_| {
+                let node = moved.expect("The nested change found the item");
+                self.handle_collision(&node, node.x, node.y, &mut grid)?;
+                self.create_add_change(&node);
+                self.handle_float(&mut grid)
+            });
+        self.finish_pending_changes(result, grid)
+    }
+
+    /// Runs `operation` on a copy of the grid nested in the item at the path, and adds its
+    /// changes to the pending ones as a `Nested` change
+    fn create_nested_change(
+        &mut self,
+        path: &str,
+        operation: impl FnOnce(&mut GridEngine) -> Result<(), GridError>,
+    ) -> Result<(), GridError> {
+        let (id, rest) = split_path(path);
+        let node = match self.get_pending_node(id) {
+            Some(node) => node,
+            None => Err(GridError::ItemNotFound { id: id.to_string() })?,
+        };
+        let mut child = match node.get_child_grid()? {
+            Some(child) => child,
+            None => Err(GridError::NoChildGrid { id: id.to_string() })?,
+        };
+
+        child.begin_transaction()?;
+        match rest {
+            Some(rest) => child.update_child(rest, operation)?,
+            None => operation(&mut child)?,
+        }
+        let changes = child.pending_changes.clone();
+        child.commit()?;
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        self.pending_changes.push(Change::Nested(NestedChangeData {
+            new_value: Node {
+                child: Some(SubGrid::from_engine(&child)),
+                ..node.clone()
+            },
+            old_value: node,
+            changes,
+        }));
+        Ok(())
+    }
+
+    /// Applies the pending changes as a single batch, or discards them if building them failed.
+    /// Within a transaction, they are kept, along with the working `grid`, until the commit.
+    fn finish_pending_changes(
+        &mut self,
+        result: Result<(), GridError>,
+        grid: Cells,
+    ) -> Result<(), GridError> {
+        match (&mut self.transaction, result) {
+            (Some(transaction), Ok(())) => {
+                transaction.grid = grid;
+                transaction.checkpoint = self.pending_changes.len();
+                Ok(())
+            }
+            (Some(transaction), Err(err)) => {
+                self.pending_changes.truncate(transaction.checkpoint);
+                Err(err)
+            }
+            (None, Ok(())) => self.apply_pending_changes(),
+            (None, Err(err)) => {
+                self.pending_changes.clear();
+                Err(err)
+            }
+        }
+    }
+
+    /// Gets a copy of the grid to build the changes of an operation on, with the changes of the
+    /// open transaction
+    fn get_working_grid(&self) -> Cells {
+        self.working_grid().clone()
+    }
+
+    /// The grid of the open transaction, or the committed grid when there is none
+    fn working_grid(&self) -> &Cells {
+        match &self.transaction {
+            Some(transaction) => &transaction.grid,
+            None => &self.grid,
+        }
+    }
+
+    fn check_no_transaction(&self) -> Result<(), GridError> {
+        if self.transaction.is_some() {
+            return Err(GridError::TransactionInProgress);
+        }
+        Ok(())
+    }
+
+    fn apply_pending_changes(&mut self) -> Result<(), GridError> {
+        let pending_changes = std::mem::take(&mut self.pending_changes);
+        self.apply_changes(&pending_changes)?;
+        self.history.record(&self.layout, pending_changes);
+        Ok(())
+    }
+
+    /// Applies the whole batch, or nothing of it if any change conflicts with the current grid.
+    /// The error of a conflict is a `ChangeConflictError` naming the first conflicting change.
+    pub fn apply_changes(&mut self, changes: &[Change]) -> Result<(), GridError> {
+        self.validate_changes(changes)?;
+
+        // The validated batch fits the current grid, so applying it does not fail halfway
+        let hash_before = self.hash;
+        self.apply_validated_changes(changes)?;
+
+        if self.events.has_listeners(&EventName::BatchChange) {
+            let grid_view = GridView::new(self);
+            self.events.trigger_event(
+                &grid_view,
+                EventName::BatchChange,
+                EventValue::BatchChange(BatchChangeValue {
+                    layout: self.layout.clone(),
+                    changes: changes.to_vec(),
+                    hash_before: hash::format_hash(hash_before),
+                    hash_after: self.get_hash(),
+                }),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Checks the batch against the current grid, as if each change was applied in order.
+    /// Every change must start from the current value of its item and place it inside the grid,
+    /// and no items may overlap once the whole batch is applied. Overlaps in between are fine,
+    /// as the changes of a collision cascade are not ordered.
+    fn validate_changes(&self, changes: &[Change]) -> Result<(), GridError> {
+        // Value of each item the batch went through so far, `None` once removed
+        let mut overrides: HashMap<&str, Option<&Node>> = HashMap::new();
+        let mut size = self.grid.size();
+        let mut changed_at: BTreeMap<&str, usize> = BTreeMap::new();
+
+        for (index, change) in changes.iter().enumerate() {
+            match change {
+                Change::Add(data) => {
+                    let node = &data.value;
+                    if current_value(&self.items, &overrides, &node.id).is_some() {
+                        let err = GridError::DuplicateId {
+                            id: node.id.clone(),
+                        };
+                        return Err(into_change_conflict(index, &node.id, err));
+                    }
+                    check_changed_node(index, node, size)?;
+                    check_changed_child(index, node)?;
+
+                    overrides.insert(&node.id, Some(node));
+                    changed_at.insert(&node.id, index);
+                }
+                Change::Remove(data) => {
+                    let node = &data.value;
+                    match current_value(&self.items, &overrides, &node.id) {
+                        Some(current) if current == node => {}
+                        Some(_) => {
+                            return Err(change_conflict(
+                                index,
+                                &node.id,
+                                "Item does not match the removed value",
+                            ))
+                        }
+                        None => {
+                            let err = GridError::ItemNotFound {
+                                id: node.id.clone(),
+                            };
+                            return Err(into_change_conflict(index, &node.id, err));
+                        }
+                    }
+
+                    overrides.insert(&node.id, None);
+                    changed_at.remove(node.id.as_str());
+                }
+                Change::Move(MoveChangeData {
+                    old_value,
+                    new_value,
+                })
+                | Change::Resize(ResizeChangeData {
+                    old_value,
+                    new_value,
+                }) => {
+                    if old_value.id != new_value.id {
+                        return Err(change_conflict(
+                            index,
+                            &new_value.id,
+                            &format!("Old value belongs to item {}", old_value.id),
+                        ));
+                    }
+                    check_current_value(
+                        index,
+                        current_value(&self.items, &overrides, &old_value.id),
+                        old_value,
+                    )?;
+                    if !only_layout_differs(old_value, new_value) {
+                        return Err(change_conflict(
+                            index,
+                            &old_value.id,
+                            "Only the position and size of the item can be updated",
+                        ));
+                    }
+                    check_updatable(
+                        old_value,
+                        new_value.x,
+                        new_value.y,
+                        new_value.w,
+                        new_value.h,
+                    )
+                    .map_err(|err| into_change_conflict(index, &old_value.id, err))?;
+                    check_changed_node(index, new_value, size)?;
+                    if new_value.child != old_value.child {
+                        check_changed_child(index, new_value)?;
+                    }
+
+                    overrides.insert(&new_value.id, Some(new_value));
+                    changed_at.insert(&new_value.id, index);
+                }
+                Change::UpdateData(data) => {
+                    let (old_value, new_value) = (&data.old_value, &data.new_value);
+                    check_current_value(
+                        index,
+                        current_value(&self.items, &overrides, &old_value.id),
+                        old_value,
+                    )?;
+                    let unchanged = Node {
+                        data: old_value.data.clone(),
+                        ..new_value.clone()
+                    };
+                    if unchanged != *old_value {
+                        return Err(change_conflict(
+                            index,
+                            &old_value.id,
+                            "Only the data of the item can be updated",
+                        ));
+                    }
+
+                    overrides.insert(&new_value.id, Some(new_value));
+                }
+                Change::Nested(data) => {
+                    let (old_value, new_value) = (&data.old_value, &data.new_value);
+                    check_current_value(
+                        index,
+                        current_value(&self.items, &overrides, &old_value.id),
+                        old_value,
+                    )?;
+                    let unchanged = Node {
+                        child: old_value.child.clone(),
+                        ..new_value.clone()
+                    };
+                    if unchanged != *old_value {
+                        return Err(change_conflict(
+                            index,
+                            &old_value.id,
+                            "Only the child grid of the item can be updated",
+                        ));
+                    }
+
+                    let child = old_value
+                        .get_child_grid()
+                        .map_err(|err| into_change_conflict(index, &old_value.id, err))?;
+                    let mut child = match child {
+                        Some(child) => child,
+                        None => {
+                            let err = GridError::NoChildGrid {
+                                id: old_value.id.clone(),
+                            };
+                            return Err(into_change_conflict(index, &old_value.id, err));
+                        }
+                    };
+                    child
+                        .apply_changes(&data.changes)
+                        .map_err(|err| into_change_conflict(index, &old_value.id, err))?;
+                    if new_value.child != Some(SubGrid::from_engine(&child)) {
+                        return Err(change_conflict(
+                            index,
+                            &old_value.id,
+                            "Child grid does not match the nested changes",
+                        ));
+                    }
+
+                    overrides.insert(&new_value.id, Some(new_value));
+                }
+                Change::GridResize(data) => {
+                    if size != (data.old_rows, data.old_cols) {
+                        return Err(change_conflict(
+                            index,
+                            "",
+                            &format!(
+                                "Expected a {}x{} grid, but it is {}x{}",
+                                data.old_rows, data.old_cols, size.0, size.1
+                            ),
+                        ));
+                    }
+                    if data.new_rows == 0 || data.new_cols == 0 {
+                        return Err(change_conflict(
+                            index,
+                            "",
+                            &format!(
+                                "The grid needs at least one row and one column, but got {}x{}",
+                                data.new_rows, data.new_cols
+                            ),
+                        ));
+                    }
+                    size = (data.new_rows, data.new_cols);
+
+                    let unchanged = self
+                        .items
+                        .values()
+                        .filter(|node| !overrides.contains_key(node.id.as_str()));
+                    for node in unchanged.chain(overrides.values().flatten().copied()) {
+                        check_bounds(size, node.x, node.y, node.w, node.h)
+                            .map_err(|err| into_change_conflict(index, &node.id, err))?;
+                    }
+                }
+            }
+        }
+
+        // Items placed by the batch, in order of their last change, against the cells of the
+        // items it did not change and against the items placed before them. Overlaps already in
+        // the grid are not caused by this batch.
+        let mut placed: Vec<(usize, &Node)> = changed_at
+            .iter()
+            .filter_map(|(id, index)| Some((*index, current_value(&self.items, &overrides, id)?)))
+            .collect();
+        placed.sort_by_key(|(index, _)| *index);
+
+        for (position, (index, node)) in placed.iter().enumerate() {
+            let unchanged = self
+                .grid
+                .collisions(node.x, node.y, node.w, node.h, &node.id)
+                .into_iter()
+                .find(|id| !overrides.contains_key(id.as_str()));
+            let overlapped = unchanged.or_else(|| {
+                placed[..position]
+                    .iter()
+                    .find(|(_, other)| other.intersects(node))
+                    .map(|(_, other)| other.id.clone())
+            });
+            if let Some(overlapped) = overlapped {
+                return Err(change_conflict(
+                    *index,
+                    &node.id,
+                    &format!("Item overlaps item {}", overlapped),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn apply_validated_changes(&mut self, changes: &[Change]) -> Result<(), GridError> {
+        for change in changes.iter() {
+            match &change {
+                Change::Add(data) => {
+                    let node = &data.value;
+
+                    node.for_cell(&mut |x, y| {
+                        update_grid(&mut self.grid, node, x, y, UpdateGridOperation::Add)
+                    })?;
+
+                    self.insert_item(node);
+                    self.update_layouts(&node.id, |layout| layout.insert_first_fit(node))?;
+                }
+                Change::Remove(data) => {
+                    let node = &data.value;
+
+                    node.for_cell(&mut |x, y| {
+                        update_grid(&mut self.grid, node, x, y, UpdateGridOperation::Remove)
+                    })?;
+
+                    self.take_item(&node.id);
+                    self.update_layouts(&node.id, |layout| layout.remove(&node.id))?;
+                }
+                Change::Move(data) => {
+                    self.apply_update(&data.old_value, &data.new_value)?;
+                }
+                Change::Resize(data) => {
+                    self.apply_update(&data.old_value, &data.new_value)?;
+                }
+                Change::UpdateData(data) => {
+                    let node = &data.new_value;
+
+                    self.insert_item(node);
+                    self.update_layouts(&node.id, |layout| {
+                        layout.update_data(&node.id, &node.data)
+                    })?;
+                }
+                Change::Nested(data) => {
+                    let node = &data.new_value;
+
+                    self.insert_item(node);
+                    self.update_layouts(&node.id, |layout| layout.update_child(node))?;
+                }
+                Change::GridResize(data) => {
+                    let (rows, cols) = self.grid.size();
+                    self.grid.resize(data.new_rows, data.new_cols);
+                    self.hash ^= hash::size_entry(&self.layout, rows, cols)
+                        ^ hash::size_entry(&self.layout, data.new_rows, data.new_cols);
+                }
+            }
+        }
+
+        self.repaint_cells(changes);
+        Ok(())
+    }
+
+    /// Repaints the cells the changes went through from the items. A node moved more than once
+    /// in a batch may have overwritten, then cleared, the cells of a node that moved over them
+    /// in between.
+    fn repaint_cells(&mut self, changes: &[Change]) {
+        let mut touched: Vec<&Node> = Vec::new();
+        for change in changes {
+            match change {
+                Change::Add(AddChangeData { value })
+                | Change::Remove(RemoveChangeData { value }) => touched.push(value),
+                Change::Move(MoveChangeData {
+                    old_value,
+                    new_value,
+                })
+                | Change::Resize(ResizeChangeData {
+                    old_value,
+                    new_value,
+                }) => touched.extend([old_value, new_value]),
+                Change::UpdateData(_) | Change::Nested(_) | Change::GridResize(_) => {}
+            }
+        }
+
+        for node in touched.iter() {
+            for_cell(node.x, node.y, node.w, node.h, &mut |x, y| {
+                self.grid.set(y, x, None);
+                Ok(())
+            })
+            .expect("Clearing a cell never fails");
+        }
+        for node in self
+            .items
+            .values()
+            .filter(|node| touched.iter().any(|touched| touched.intersects(node)))
+        {
+            for_cell(node.x, node.y, node.w, node.h, &mut |x, y| {
+                self.grid.set(y, x, Some(&node.id));
+                Ok(())
+            })
+            .expect("Painting a cell never fails");
+        }
+    }
+
+    fn apply_update(&mut self, old_node: &Node, node: &Node) -> Result<(), GridError> {
+        old_node.for_cell(&mut |x, y| {
+            update_grid(&mut self.grid, old_node, x, y, UpdateGridOperation::Remove)
+        })?;
+
+        self.insert_item(node);
+        node.for_cell(&mut |x, y| update_grid(&mut self.grid, node, x, y, UpdateGridOperation::Add))
+    }
+
+    /// Inserts the node in the active layout, replacing its entry in the running hash
+    fn insert_item(&mut self, node: &Node) {
+        self.hash ^= hash::node_entry(&self.layout, node);
+        if let Some(old_node) = self.items.insert(node.id.clone(), node.clone()) {
+            self.hash ^= hash::node_entry(&self.layout, &old_node);
+        }
+    }
+
+    /// Removes the node from the active layout, removing its entry from the running hash
+    fn take_item(&mut self, id: &str) {
+        if let Some(old_node) = self.items.remove(id) {
+            self.hash ^= hash::node_entry(&self.layout, &old_node);
+        }
+    }
+
+    /// Runs `update` on every stored layout, replacing the entries it changes in the running
+    /// hash. It may only change the item and the layout size.
+    fn update_layouts(
+        &mut self,
+        id: &str,
+        update: impl Fn(&mut Layout) -> Result<(), GridError>,
+    ) -> Result<(), GridError> {
+        for (name, layout) in self.layouts.iter_mut() {
+            let before = layout.hash_entries(name, id);
+            let result = update(layout);
+            self.hash ^= before ^ layout.hash_entries(name, id);
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Hash of the grid and of every layout, the same as the one of its `GridView`, without
+    /// computing it from scratch
+    pub fn get_hash(&self) -> String {
+        hash::format_hash(self.hash)
+    }
+
+    pub fn get_grid_view(&self) -> GridView {
+        GridView::new(self)
+    }
+
+    /// Gets the item covering the cell, if any
+    pub fn item_at(&self, x: usize, y: usize) -> Option<&Node> {
+        query::item_at(&self.grid, &self.items, x, y)
+    }
+
+    /// Gets the items intersecting the rectangle, sorted by id
+    pub fn items_in_rect(&self, x: usize, y: usize, w: usize, h: usize) -> Vec<&Node> {
+        query::items_in_rect(&self.items, x, y, w, h)
+    }
+
+    /// Gets the nearest item on each side of the item
+    pub fn neighbors(&self, id: &str) -> Result<Neighbors, GridError> {
+        query::neighbors(&self.items, id)
+    }
+
+    /// Gets the smallest rectangle containing every item, `None` if there is no item
+    pub fn bounding_box(&self) -> Option<Rect> {
+        query::bounding_box(&self.items)
+    }
+
+    /// Gets the ratio of the cells covered by items, from 0 to 1
+    pub fn occupancy(&self) -> f64 {
+        query::occupancy(&self.grid, &self.items)
+    }
+}
+
+impl TryFrom<&Vec<u8>> for GridEngine {
+    type Error = GridError;
+
+    fn try_from(bytes: &Vec<u8>) -> Result<Self, Self::Error> {
+        let serialized = String::from_utf8(bytes.clone())?;
+
+        GridEngine::from_str(&serialized)
+    }
+}
+
+impl From<&GridEngine> for Vec<u8> {
+    fn from(grid_engine: &GridEngine) -> Self {
+        let serialized =
+            serde_json::to_string(grid_engine).expect("Failed to serialize GridEngine");
+        serialized.into_bytes()
+    }
+}
+
+impl From<&GridView> for GridEngine {
+    fn from(grid_view: &GridView) -> Self {
+        GridEngine {
+            grid: grid_view.grid.clone(),
+            items: grid_view.items.clone(),
+            layout: grid_view.layout.clone(),
+            layouts: grid_view.layouts.clone(),
+            pending_changes: Vec::new(),
+            history: History::default(),
+            transaction: None,
+            collision_strategy: CollisionStrategy::default(),
+            swap: false,
+            float: true,
+            max_rows: None,
+            hash: grid_view.compute_hash(),
+            events: EventListener::default(),
+        }
+    }
+}
+
+impl From<GridView> for GridEngine {
+    fn from(grid_view: GridView) -> Self {
+        GridEngine::from(&grid_view)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::test_utils::record_batches;
+
+    use super::*;
+
+    #[test]
+    fn test_for_cell() {
+        let mut results = Vec::new();
+        let mut callback = |x: usize, y: usize| {
+            results.push((x, y));
+            Ok(())
+        };
+
+        for_cell(1, 2, 2, 2, &mut callback).unwrap();
+
+        assert_eq!(results, vec![(1, 2), (1, 3), (2, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn test_add_item() {
+        let mut engine = GridEngine::new(10, 10);
+        let item_0_id = engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+
+        assert!(engine.items.len() == 1);
+        for_cell(0, 0, 2, 2, &mut |x, y| {
+            assert_eq!(engine.grid.get(y, x).unwrap().as_ref().unwrap(), &item_0_id);
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_add_item_handle_duplicated_id() {
+        let mut engine = GridEngine::new(10, 10);
+        engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+
+        assert!(engine.add_item("0".to_string(), 0, 0, 2, 2).is_err())
+    }
+
+    #[test]
+    fn test_add_item_handle_collision() {
+        let mut engine = GridEngine::new(10, 10);
+        let item_0_id = engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+        let item_1_id = engine.add_item("1".to_string(), 0, 0, 2, 2).unwrap();
+
+        // Item 0 should stay in position 0, 0
+        let item_0 = engine.items.get(&item_0_id).unwrap();
+        assert_eq!(item_0.x, 0);
+        assert_eq!(item_0.y, 2);
+        item_0
+            .for_cell(&mut |x, y| {
+                assert_eq!(engine.grid.get(y, x).unwrap().as_ref().unwrap(), &item_0_id);
+                Ok(())
+            })
+            .unwrap();
+
+        // Item 1 should go to position 0, 2
+        let item_1 = engine.items.get(&item_1_id).unwrap();
+        assert_eq!(item_1.x, 0);
+        assert_eq!(item_1.y, 0);
+        item_1
+            .for_cell(&mut |x, y| {
+                assert_eq!(engine.grid.get(y, x).unwrap().as_ref().unwrap(), &item_1_id);
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_remove_item() {
+        let mut engine = GridEngine::new(10, 10);
+        let item_0_id = engine.add_item("0".to_string(), 0, 0, 2, 3).unwrap();
+        engine.remove_item(&item_0_id).unwrap();
+        for_cell(0, 0, 2, 3, &mut |x, y| {
+            let value = engine.grid.get(y, x).unwrap();
+            assert_eq!(value, &None);
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_move_item() {
+        let mut engine = GridEngine::new(10, 10);
+        let item_0_id = engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+        engine.move_item(&item_0_id, 1, 1).unwrap();
+
+        // Asserts that its present on the new position
+        for_cell(1, 1, 2, 2, &mut |x, y| {
+            let item_on_expected_position = engine.grid.get(y, x).unwrap().as_ref().unwrap();
+            assert_eq!(item_on_expected_position, &item_0_id);
+            Ok(())
+        })
+        .unwrap();
+
+        // Asserts that its not present on the old position
+        for_cell(0, 0, 1, 1, &mut |x, y| {
+            assert_eq!(engine.grid.get(y, x).unwrap(), &None);
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_move_item_handle_collision() {
+        let mut engine = GridEngine::new(10, 10);
+        let item_0_id = engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+        let item_1_id = engine.add_item("1".to_string(), 0, 2, 2, 2).unwrap();
+        engine.move_item("0", 0, 1).unwrap();
+
+        // Item 0 should go to position 0, 1
+        let item_0 = engine.items.get(&item_0_id).unwrap();
+        assert_eq!(item_0.x, 0);
+        assert_eq!(item_0.y, 1);
+        item_0
+            .for_cell(&mut |x, y| {
                 assert_eq!(engine.grid.get(y, x).unwrap().as_ref().unwrap(), &item_0_id);
                 Ok(())
             })
             .unwrap();
 
-        // Item 1 should go to position 0, 3
-        let item_1 = engine.items.get(&item_1_id).unwrap();
-        assert_eq!(item_1.x, 0);
-        assert_eq!(item_1.y, 3);
-        item_1
-            .for_cell(&mut |x, y| {
-                assert_eq!(engine.grid.get(y, x).unwrap().as_ref().unwrap(), &item_1_id);
+        // Item 1 should go to position 0, 3
+        let item_1 = engine.items.get(&item_1_id).unwrap();
+        assert_eq!(item_1.x, 0);
+        assert_eq!(item_1.y, 3);
+        item_1
+            .for_cell(&mut |x, y| {
+                assert_eq!(engine.grid.get(y, x).unwrap().as_ref().unwrap(), &item_1_id);
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_move_item_handle_collision_pushing_to_side() {
+        let mut engine = GridEngine::new(10, 12);
+        engine.set_collision_strategy(CollisionStrategy::PushSide);
+        engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+        engine.add_item("1".to_string(), 4, 0, 2, 2).unwrap();
+        engine.add_item("2".to_string(), 10, 0, 2, 2).unwrap();
+
+        let batches = record_batches(&mut engine);
+
+        // Item 1 is mostly on the right of the new position, so its pushed to the right
+        engine.move_item("0", 3, 0).unwrap();
+        let item_1 = engine.items.get("1").unwrap();
+        assert_eq!((item_1.x, item_1.y), (5, 0));
+        assert_eq!(
+            batches.lock().unwrap()[0].changes[0],
+            Change::Move(MoveChangeData {
+                old_value: Node::new("1".to_string(), 4, 0, 2, 2),
+                new_value: Node::new("1".to_string(), 5, 0, 2, 2),
+            })
+        );
+
+        // Item 2 has no room on the right, so its pushed to the left
+        batches.lock().unwrap().clear();
+        engine.move_item("1", 9, 0).unwrap();
+        let item_2 = engine.items.get("2").unwrap();
+        assert_eq!((item_2.x, item_2.y), (7, 0));
+        assert_eq!(engine.grid.get(0, 7).unwrap().as_ref().unwrap(), "2");
+        assert_eq!(engine.grid.get(0, 11).unwrap(), &None);
+    }
+
+    #[test]
+    fn test_push_side_falls_back_to_push_down() {
+        let mut engine = GridEngine::new(10, 4);
+        engine.set_collision_strategy(CollisionStrategy::PushSide);
+        engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+        engine.add_item("1".to_string(), 2, 0, 2, 2).unwrap();
+
+        engine.add_item("2".to_string(), 1, 0, 2, 2).unwrap();
+
+        let item_0 = engine.items.get("0").unwrap();
+        assert_eq!((item_0.x, item_0.y), (0, 2));
+        let item_1 = engine.items.get("1").unwrap();
+        assert_eq!((item_1.x, item_1.y), (2, 2));
+    }
+
+    #[test]
+    fn test_move_item_handle_collision_pushing_up() {
+        let mut engine = GridEngine::new(10, 10);
+        engine.set_collision_strategy(CollisionStrategy::PushUp);
+        engine.add_item("0".to_string(), 0, 2, 2, 2).unwrap();
+        engine.add_item("1".to_string(), 0, 6, 2, 2).unwrap();
+
+        // There is room above the new position, so item 0 goes up
+        engine.move_item("1", 0, 3).unwrap();
+        let item_0 = engine.items.get("0").unwrap();
+        assert_eq!((item_0.x, item_0.y), (0, 1));
+
+        // No room above, falls back to pushing down
+        engine.move_item("1", 0, 0).unwrap();
+        let item_0 = engine.items.get("0").unwrap();
+        assert_eq!((item_0.x, item_0.y), (0, 2));
+    }
+
+    #[test]
+    fn test_move_item_swapping() {
+        let mut engine = GridEngine::new(10, 12);
+        engine.set_swap(true);
+        engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+        engine.add_item("1".to_string(), 4, 0, 2, 2).unwrap();
+        engine.add_item("2".to_string(), 4, 2, 2, 1).unwrap();
+        engine
+            .add_node(
+                Node::new("3".to_string(), 8, 0, 2, 2).with_flags(NodeFlags {
+                    locked: true,
+                    no_move: false,
+                }),
+            )
+            .unwrap();
+        let mut peer = GridEngine::from(&engine.get_grid_view());
+
+        let batches = record_batches(&mut engine);
+
+        // Dropped exactly on a node of the same size, they trade places
+        engine.move_item("0", 4, 0).unwrap();
+        let batch = batches.lock().unwrap().pop().unwrap();
+        assert_eq!(
+            batch.changes,
+            vec![
+                Change::Move(MoveChangeData {
+                    old_value: Node::new("0".to_string(), 0, 0, 2, 2),
+                    new_value: Node::new("0".to_string(), 4, 0, 2, 2),
+                }),
+                Change::Move(MoveChangeData {
+                    old_value: Node::new("1".to_string(), 4, 0, 2, 2),
+                    new_value: Node::new("1".to_string(), 0, 0, 2, 2),
+                }),
+            ]
+        );
+        assert_eq!(engine.grid.get(0, 0).unwrap().as_deref(), Some("1"));
+        assert_eq!(engine.grid.get(1, 5).unwrap().as_deref(), Some("0"));
+        peer.apply_changes(&batch.changes).unwrap();
+        assert_eq!(peer.get_hash(), engine.get_hash());
+
+        engine.undo().unwrap();
+        assert_eq!(
+            engine.get_hash(),
+            batches.lock().unwrap().pop().unwrap().hash_after
+        );
+        let item_0 = engine.items.get("0").unwrap();
+        assert_eq!((item_0.x, item_0.y), (0, 0));
+
+        // A node of another size is pushed down
+        engine.move_item("0", 4, 2).unwrap();
+        let item_2 = engine.items.get("2").unwrap();
+        assert_eq!((item_2.x, item_2.y), (4, 4));
+
+        // So is a node that is not exactly under the moved one
+        engine.move_item("0", 5, 0).unwrap();
+        let item_1 = engine.items.get("1").unwrap();
+        assert_eq!((item_1.x, item_1.y), (4, 2));
+
+        // A locked node can not be swapped
+        assert!(matches!(
+            engine.move_item("0", 8, 0),
+            Err(GridError::Collision { .. })
+        ));
+
+        // Swapping goes with any collision strategy, which handles the other collisions
+        let mut engine = GridEngine::new(4, 8);
+        engine.set_swap(true);
+        engine.set_collision_strategy(CollisionStrategy::PushSide);
+        engine.add_item("a".to_string(), 0, 0, 2, 2).unwrap();
+        engine.add_item("b".to_string(), 2, 0, 2, 2).unwrap();
+        engine.add_item("c".to_string(), 4, 0, 1, 2).unwrap();
+        engine.move_item("a", 2, 0).unwrap();
+        let item_b = engine.items.get("b").unwrap();
+        assert_eq!((item_b.x, item_b.y), (0, 0));
+
+        engine.move_item("a", 4, 0).unwrap();
+        let item_c = engine.items.get("c").unwrap();
+        assert_eq!(item_c.y, 0);
+        assert_ne!(item_c.x, 4);
+    }
+
+    #[test]
+    fn test_float_disabled_compacts_on_every_operation() {
+        let mut engine = GridEngine::new(10, 10);
+        engine.set_float(false);
+
+        let batches = record_batches(&mut engine);
+
+        engine.add_item("0".to_string(), 0, 3, 2, 2).unwrap();
+        assert_eq!(engine.items.get("0").unwrap().y, 0);
+        assert_eq!(engine.grid.get(3, 0).unwrap(), &None);
+
+        engine.add_item("1".to_string(), 0, 5, 2, 2).unwrap();
+        assert_eq!(engine.items.get("1").unwrap().y, 2);
+
+        engine.remove_item("0").unwrap();
+        assert_eq!(engine.items.get("1").unwrap().y, 0);
+
+        // Each operation and its compaction are sent as a single batch
+        let batches = batches.lock().unwrap();
+        assert_eq!(batches.len(), 3);
+        assert_eq!(
+            batches[2].changes,
+            vec![
+                Change::Remove(RemoveChangeData {
+                    value: Node::new("0".to_string(), 0, 0, 2, 2),
+                }),
+                Change::Move(MoveChangeData {
+                    old_value: Node::new("1".to_string(), 0, 2, 2, 2),
+                    new_value: Node::new("1".to_string(), 0, 0, 2, 2),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut engine = GridEngine::new(10, 10);
+        engine.add_item("0".to_string(), 0, 2, 2, 2).unwrap();
+        engine.add_item("1".to_string(), 1, 6, 2, 2).unwrap();
+        engine.add_item("2".to_string(), 4, 5, 2, 1).unwrap();
+
+        engine.compact().unwrap();
+
+        let positions: Vec<(usize, usize)> = engine
+            .get_grid_view()
+            .get_nodes()
+            .iter()
+            .map(|node| (node.x, node.y))
+            .collect();
+        assert_eq!(positions, vec![(0, 0), (1, 2), (4, 0)]);
+    }
+
+    fn get_out_of_bounds(err: &GridError) -> &OutOfBoundsError {
+        match err {
+            GridError::OutOfBounds(out_of_bounds) => out_of_bounds,
+            // Batch errors wrap it in a conflict
+            GridError::InvalidChange(ChangeConflictError {
+                source: Some(source),
+                ..
+            }) => get_out_of_bounds(source),
+            _ => panic!("Expected an OutOfBoundsError, got {:?}", err),
+        }
+    }
+
+    fn get_conflict(err: &GridError) -> &ChangeConflictError {
+        match err {
+            GridError::InvalidChange(conflict) => conflict,
+            _ => panic!("Expected a ChangeConflictError, got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_add_item_out_of_bounds() {
+        let mut engine = GridEngine::new(10, 12);
+        let err = engine.add_item("0".to_string(), 11, 0, 2, 2).unwrap_err();
+
+        assert_eq!(
+            get_out_of_bounds(&err),
+            &OutOfBoundsError {
+                x: 11,
+                y: 0,
+                w: 2,
+                h: 2,
+                rows: 10,
+                cols: 12
+            }
+        );
+        assert!(engine.items.is_empty());
+        let (rows, cols) = engine.grid.size();
+        assert!((0..rows).all(|y| (0..cols).all(|x| engine.grid.get(y, x) == Some(&None))));
+    }
+
+    #[test]
+    fn test_move_item_out_of_bounds() {
+        let mut engine = GridEngine::new(4, 4);
+        engine.set_max_rows(Some(4));
+        engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+        engine.add_item("1".to_string(), 0, 2, 2, 2).unwrap();
+        let view_before = engine.get_grid_view().serialized_as_str();
+
+        let err = engine.move_item("0", 3, 0).unwrap_err();
+        assert_eq!(get_out_of_bounds(&err).x, 3);
+
+        // Item 1 would be pushed past the last row
+        let err = engine.move_item("0", 0, 1).unwrap_err();
+        assert_eq!(get_out_of_bounds(&err).y, 3);
+
+        assert_eq!(engine.get_grid_view().serialized_as_str(), view_before);
+    }
+
+    #[test]
+    fn test_apply_changes_out_of_bounds() {
+        let mut engine = GridEngine::new(4, 4);
+        engine.set_max_rows(Some(4));
+        engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+        let view_before = engine.get_grid_view().serialized_as_str();
+
+        let result = engine.apply_changes(&[
+            Change::Add(AddChangeData {
+                value: Node::new("1".to_string(), 2, 0, 2, 2),
+            }),
+            Change::Move(MoveChangeData {
+                old_value: Node::new("0".to_string(), 0, 0, 2, 2),
+                new_value: Node::new("0".to_string(), 0, 3, 2, 2),
+            }),
+        ]);
+
+        assert_eq!(get_out_of_bounds(&result.unwrap_err()).y, 3);
+        assert_eq!(engine.get_grid_view().serialized_as_str(), view_before);
+    }
+
+    #[test]
+    fn test_apply_changes_conflicts() {
+        let mut engine = GridEngine::new(4, 4);
+        engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+        engine.add_item("1".to_string(), 2, 0, 2, 2).unwrap();
+        let view_before = engine.get_grid_view().serialized_as_str();
+
+        let move_change = |id: &str, x, y, new_x, new_y| {
+            Change::Move(MoveChangeData {
+                old_value: Node::new(id.to_string(), x, y, 2, 2),
+                new_value: Node::new(id.to_string(), new_x, new_y, 2, 2),
+            })
+        };
+
+        // Stale old value, as if item 0 was moved in between
+        let err = engine
+            .apply_changes(&[move_change("1", 2, 0, 2, 2), move_change("0", 0, 1, 0, 2)])
+            .unwrap_err();
+        let conflict = get_conflict(&err);
+        assert_eq!((conflict.index, conflict.id.as_str()), (1, "0"));
+
+        // Landing over another item
+        let err = engine
+            .apply_changes(&[move_change("0", 0, 0, 1, 0)])
+            .unwrap_err();
+        let conflict = get_conflict(&err);
+        assert_eq!((conflict.index, conflict.id.as_str()), (0, "0"));
+
+        let err = engine
+            .apply_changes(&[Change::Remove(RemoveChangeData {
+                value: Node::new("2".to_string(), 0, 0, 1, 1),
+            })])
+            .unwrap_err();
+        assert!(matches!(
+            get_conflict(&err).source.as_deref(),
+            Some(GridError::ItemNotFound { id }) if id == "2"
+        ));
+
+        let err = engine
+            .apply_changes(&[Change::Add(AddChangeData {
+                value: Node::new("1".to_string(), 0, 2, 1, 1),
+            })])
+            .unwrap_err();
+        assert!(matches!(
+            get_conflict(&err).source.as_deref(),
+            Some(GridError::DuplicateId { id }) if id == "1"
+        ));
+
+        let err = engine
+            .apply_changes(&[Change::GridResize(GridResizeChangeData {
+                old_rows: 4,
+                old_cols: 4,
+                new_rows: 4,
+                new_cols: 3,
+            })])
+            .unwrap_err();
+        assert_eq!(get_out_of_bounds(&err).cols, 3);
+
+        assert_eq!(engine.get_grid_view().serialized_as_str(), view_before);
+
+        // Even without items, the grid keeps a row and a column
+        let mut empty = GridEngine::new(4, 4);
+        for (new_rows, new_cols) in [(5, 0), (0, 4)] {
+            let err = empty
+                .apply_changes(&[Change::GridResize(GridResizeChangeData {
+                    old_rows: 4,
+                    old_cols: 4,
+                    new_rows,
+                    new_cols,
+                })])
+                .unwrap_err();
+            assert_eq!(get_conflict(&err).index, 0);
+        }
+        assert_eq!(empty.grid.size(), (4, 4));
+        empty.add_item("0".to_string(), 0, 0, 1, 1).unwrap();
+
+        // Swapping items overlaps them in between, which is fine
+        engine
+            .apply_changes(&[move_change("0", 0, 0, 2, 0), move_change("1", 2, 0, 0, 0)])
+            .unwrap();
+        assert_eq!(engine.grid.get(0, 0), Some(&Some("1".to_string())));
+        assert_eq!(engine.grid.get(0, 2), Some(&Some("0".to_string())));
+    }
+
+    #[test]
+    fn test_grow_rows() {
+        let mut engine = GridEngine::new(4, 4);
+
+        let batches = record_batches(&mut engine);
+
+        engine.add_item("0".to_string(), 0, 3, 2, 2).unwrap();
+        assert_eq!(engine.grid.size(), (5, 4));
+
+        // Pushes item 0 down to the rows 4 and 5
+        engine.add_item("1".to_string(), 0, 1, 2, 3).unwrap();
+        assert_eq!(engine.grid.size(), (6, 4));
+        assert_eq!(engine.items.get("0").unwrap().y, 4);
+
+        let batches = batches.lock().unwrap();
+        assert_eq!(
+            batches[1].changes[0],
+            Change::GridResize(GridResizeChangeData {
+                old_rows: 5,
+                old_cols: 4,
+                new_rows: 6,
+                new_cols: 4,
+            })
+        );
+
+        // A peer applying the same changes ends up with the same grid
+        let mut peer = GridEngine::new(4, 4);
+        for batch in batches.iter() {
+            peer.apply_changes(&batch.changes).unwrap();
+        }
+        assert_eq!(peer.get_grid_view().hash(), engine.get_grid_view().hash());
+    }
+
+    #[test]
+    fn test_grow_rows_up_to_max_rows() {
+        let mut engine = GridEngine::new(4, 4);
+        engine.set_max_rows(Some(6));
+        engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+
+        engine.add_item("1".to_string(), 2, 2, 2, 4).unwrap();
+        assert_eq!(engine.grid.size(), (6, 4));
+
+        let err = engine.add_item("2".to_string(), 2, 3, 2, 4).unwrap_err();
+        assert_eq!(get_out_of_bounds(&err).rows, 6);
+        assert_eq!(engine.grid.size(), (6, 4));
+    }
+
+    #[test]
+    fn test_grow_rows_from_empty_grid() {
+        for storage in [StorageKind::Dense, StorageKind::Sparse] {
+            // A grid without rows keeps its columns, also once serialized
+            let mut engine = GridEngine::with_storage(0, 12, storage);
+            assert_eq!(engine.grid.size(), (0, 12));
+            let serialized = engine.get_grid_view().serialized_as_str();
+            let loaded = GridEngine::try_from(&serialized.into_bytes()).unwrap();
+            assert_eq!(loaded.grid.size(), (0, 12));
+
+            let batches = record_batches(&mut engine);
+            engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+            engine.add_item("1".to_string(), 10, 1, 2, 3).unwrap();
+            assert_eq!(engine.grid.size(), (4, 12));
+            assert_eq!(engine.grid.get(3, 11).unwrap().as_deref(), Some("1"));
+
+            let mut peer = GridEngine::new(0, 12);
+            for batch in batches.lock().unwrap().iter() {
+                peer.apply_changes(&batch.changes).unwrap();
+            }
+            assert_eq!(peer.get_grid_view().hash(), engine.get_grid_view().hash());
+        }
+    }
+
+    #[test]
+    fn test_resize_item() {
+        let mut engine = GridEngine::new(10, 10);
+        engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+        engine.add_item("1".to_string(), 0, 2, 2, 2).unwrap();
+
+        let batches = record_batches(&mut engine);
+
+        engine.resize_item("0", 3, 3).unwrap();
+
+        let item_0 = engine.items.get("0").unwrap();
+        assert_eq!((item_0.x, item_0.y, item_0.w, item_0.h), (0, 0, 3, 3));
+        for_cell(0, 0, 3, 3, &mut |x, y| {
+            assert_eq!(engine.grid.get(y, x).unwrap().as_ref().unwrap(), "0");
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(engine.items.get("1").unwrap().y, 3);
+
+        assert_eq!(
+            batches.lock().unwrap()[0].changes,
+            vec![
+                Change::Move(MoveChangeData {
+                    old_value: Node::new("1".to_string(), 0, 2, 2, 2),
+                    new_value: Node::new("1".to_string(), 0, 3, 2, 2),
+                }),
+                Change::Resize(ResizeChangeData {
+                    old_value: Node::new("0".to_string(), 0, 0, 2, 2),
+                    new_value: Node::new("0".to_string(), 0, 0, 3, 3),
+                }),
+            ]
+        );
+
+        // Shrinking frees the cells left behind
+        engine.resize_item("0", 1, 1).unwrap();
+        assert_eq!(engine.grid.get(0, 1).unwrap(), &None);
+        assert_eq!(engine.grid.get(1, 0).unwrap(), &None);
+
+        assert!(engine.resize_item("0", 0, 1).is_err());
+        assert!(engine.resize_item("missing", 1, 1).is_err());
+    }
+
+    #[test]
+    fn test_update_item() {
+        let mut engine = GridEngine::new(10, 10);
+        engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+        engine.add_item("1".to_string(), 4, 4, 2, 2).unwrap();
+
+        engine.update_item("0", 4, 3, 1, 2).unwrap();
+
+        let item_0 = engine.items.get("0").unwrap();
+        assert_eq!((item_0.x, item_0.y, item_0.w, item_0.h), (4, 3, 1, 2));
+        assert_eq!(engine.grid.get(0, 0).unwrap(), &None);
+        assert_eq!(engine.items.get("1").unwrap().y, 5);
+    }
+
+    fn get_constraint_error(err: &GridError) -> &SizeConstraintError {
+        match err {
+            GridError::SizeConstraint(constraint_error) => constraint_error,
+            GridError::InvalidChange(ChangeConflictError {
+                source: Some(source),
+                ..
+            }) => get_constraint_error(source),
+            _ => panic!("Expected a SizeConstraintError, got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_size_constraints() {
+        let mut engine = GridEngine::new(10, 12);
+        let constraints = SizeConstraints {
+            min_w: Some(2),
+            max_w: Some(6),
+            min_h: Some(2),
+            max_h: None,
+        };
+
+        let err = engine
+            .add_node(Node::new("0".to_string(), 0, 0, 1, 2).with_size_constraints(constraints))
+            .unwrap_err();
+        assert_eq!(get_constraint_error(&err).w, 1);
+        assert!(engine.items.is_empty());
+
+        engine
+            .add_node(Node::new("0".to_string(), 0, 0, 2, 2).with_size_constraints(constraints))
+            .unwrap();
+
+        let err = engine.resize_item("0", 7, 2).unwrap_err();
+        assert_eq!(get_constraint_error(&err).max_w, Some(6));
+        let err = engine.update_item("0", 1, 1, 2, 1).unwrap_err();
+        assert_eq!(get_constraint_error(&err).h, 1);
+        engine.resize_item("0", 6, 8).unwrap();
+
+        // Remote changes are checked as well
+        let node = engine.items.get("0").unwrap().clone();
+        let err = engine
+            .apply_changes(&[Change::Resize(ResizeChangeData {
+                new_value: Node {
+                    w: 1,
+                    ..node.clone()
+                },
+                old_value: node.clone(),
+            })])
+            .unwrap_err();
+        assert_eq!(get_constraint_error(&err).min_w, Some(2));
+
+        // Remote changes can not rewrite the constraints while resizing the item
+        let err = engine
+            .apply_changes(&[Change::Resize(ResizeChangeData {
+                new_value: Node {
+                    w: 1,
+                    min_w: None,
+                    ..node.clone()
+                },
+                old_value: node,
+            })])
+            .unwrap_err();
+        assert_eq!(
+            get_conflict(&err).reason,
+            "Only the position and size of the item can be updated"
+        );
+        assert_eq!(engine.items.get("0").unwrap().w, 6);
+
+        // Constraints survive the serialization
+        let serialized = engine.get_grid_view().serialized_as_str();
+        let deserialized_engine = GridEngine::from_str(&serialized).unwrap();
+        assert_eq!(
+            deserialized_engine
+                .items
+                .get("0")
+                .unwrap()
+                .get_size_constraints(),
+            constraints
+        );
+    }
+
+    #[test]
+    fn test_locked_items() {
+        let mut engine = GridEngine::new(10, 10);
+        let locked = NodeFlags {
+            locked: true,
+            no_move: false,
+        };
+        engine
+            .add_node(Node::new("header".to_string(), 0, 0, 4, 1).with_flags(locked))
+            .unwrap();
+        engine
+            .add_node(
+                Node::new("pinned".to_string(), 0, 4, 2, 2).with_flags(NodeFlags {
+                    locked: false,
+                    no_move: true,
+                }),
+            )
+            .unwrap();
+        engine.add_item("0".to_string(), 0, 1, 2, 2).unwrap();
+
+        assert!(engine.move_item("header", 0, 2).is_err());
+        assert!(engine.resize_item("header", 2, 1).is_err());
+        assert!(engine.move_item("pinned", 2, 4).is_err());
+        engine.resize_item("pinned", 2, 1).unwrap();
+
+        // Moving over a locked item is rejected, without changing anything
+        let view_before = engine.get_grid_view().serialized_as_str();
+        assert!(engine.move_item("0", 1, 0).is_err());
+        assert!(engine.add_item("1".to_string(), 0, 4, 2, 2).is_err());
+        assert_eq!(engine.get_grid_view().serialized_as_str(), view_before);
+
+        // Item 0 is pushed below the pinned item instead of over it
+        engine.add_item("1".to_string(), 0, 2, 2, 2).unwrap();
+        let item_0 = engine.items.get("0").unwrap();
+        assert_eq!((item_0.x, item_0.y), (0, 5));
+        assert_eq!(engine.items.get("pinned").unwrap().y, 4);
+
+        // Remote changes can not move them either, nor unlock them on the way
+        let header = engine.items.get("header").unwrap().clone();
+        let pinned = engine.items.get("pinned").unwrap().clone();
+        let move_change = |node: &Node, new_value: Node| {
+            Change::Move(MoveChangeData {
+                old_value: node.clone(),
+                new_value,
+            })
+        };
+        let err = engine
+            .apply_changes(&[move_change(
+                &header,
+                Node {
+                    y: 8,
+                    ..header.clone()
+                },
+            )])
+            .unwrap_err();
+        assert!(matches!(
+            get_conflict(&err).source.as_deref(),
+            Some(GridError::Locked { id, .. }) if id == "header"
+        ));
+        let err = engine
+            .apply_changes(&[move_change(
+                &header,
+                Node {
+                    y: 8,
+                    locked: false,
+                    ..header.clone()
+                },
+            )])
+            .unwrap_err();
+        assert_eq!(
+            get_conflict(&err).reason,
+            "Only the position and size of the item can be updated"
+        );
+        let err = engine
+            .apply_changes(&[move_change(
+                &pinned,
+                Node {
+                    x: 4,
+                    ..pinned.clone()
+                },
+            )])
+            .unwrap_err();
+        assert!(matches!(
+            get_conflict(&err).source.as_deref(),
+            Some(GridError::NoMove { id }) if id == "pinned"
+        ));
+        assert!(matches!(
+            engine.move_item("pinned", 4, 4),
+            Err(GridError::NoMove { .. })
+        ));
+
+        // Flags survive the serialization
+        let serialized = engine.get_grid_view().serialized_as_str();
+        let deserialized_engine = GridEngine::from_str(&serialized).unwrap();
+        assert_eq!(
+            deserialized_engine.items.get("header").unwrap().get_flags(),
+            locked
+        );
+    }
+
+    #[test]
+    fn test_compact_keeps_immovable_items() {
+        let mut engine = GridEngine::new(10, 10);
+        engine
+            .add_node(
+                Node::new("pinned".to_string(), 0, 3, 2, 1).with_flags(NodeFlags {
+                    locked: false,
+                    no_move: true,
+                }),
+            )
+            .unwrap();
+        engine.add_item("0".to_string(), 0, 6, 2, 2).unwrap();
+
+        engine.compact().unwrap();
+
+        assert_eq!(engine.items.get("pinned").unwrap().y, 3);
+        assert_eq!(engine.items.get("0").unwrap().y, 4);
+    }
+
+    #[test]
+    fn test_add_item_auto() {
+        let mut engine = GridEngine::new(4, 4);
+        engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+        engine.add_item("1".to_string(), 2, 1, 2, 2).unwrap();
+
+        assert_eq!(
+            engine.get_free_positions(2, 1),
+            vec![(2, 0), (0, 2), (0, 3), (1, 3), (2, 3)]
+        );
+        assert_eq!(engine.get_free_positions(4, 1), vec![(0, 3)]);
+        assert_eq!(engine.get_free_positions(5, 1), vec![]);
+
+        engine.add_item_auto("2".to_string(), 2, 1).unwrap();
+        let item_2 = engine.items.get("2").unwrap();
+        assert_eq!((item_2.x, item_2.y), (2, 0));
+
+        // Nothing fits, so it goes to new rows
+        engine.add_item_auto("3".to_string(), 3, 2).unwrap();
+        let item_3 = engine.items.get("3").unwrap();
+        assert_eq!((item_3.x, item_3.y), (0, 3));
+        assert_eq!(engine.grid.size(), (5, 4));
+
+        assert!(engine.add_item_auto("4".to_string(), 5, 1).is_err());
+        engine.set_max_rows(Some(5));
+        assert!(engine.add_item_auto("4".to_string(), 4, 1).is_err());
+        assert!(matches!(
+            engine.add_item_auto("4".to_string(), 0, 1),
+            Err(GridError::InvalidSize { w: 0, h: 1 })
+        ));
+
+        // Inside a transaction, the cells taken by earlier operations are not free
+        let mut engine = GridEngine::new(2, 4);
+        engine
+            .transaction(|engine| {
+                engine.add_item("0".to_string(), 0, 0, 2, 2)?;
+                assert_eq!(engine.get_free_positions(2, 2), vec![(2, 0)]);
+                assert!(engine.can_fit(2, 2));
+                engine.add_item_auto("1".to_string(), 2, 2)?;
+                assert_eq!(engine.get_free_positions(1, 1), vec![]);
+                assert!(!engine.can_fit(1, 1));
+                Ok(())
+            })
+            .unwrap();
+        let item_1 = engine.items.get("1").unwrap();
+        assert_eq!((item_1.x, item_1.y), (2, 0));
+    }
+
+    #[test]
+    fn test_set_cols() {
+        let mut engine = GridEngine::new(4, 12);
+        engine.add_item("0".to_string(), 0, 0, 6, 2).unwrap();
+        engine.add_item("1".to_string(), 6, 0, 6, 2).unwrap();
+        engine.add_item("2".to_string(), 4, 2, 4, 1).unwrap();
+
+        let batches = record_batches(&mut engine);
+
+        engine.set_cols(6).unwrap();
+        assert_eq!(engine.grid.size(), (4, 6));
+        let positions: Vec<_> = engine
+            .items
+            .values()
+            .map(|node| (node.x, node.y, node.w, node.h))
+            .collect();
+        assert_eq!(positions, vec![(0, 0, 3, 2), (3, 0, 3, 2), (2, 2, 2, 1)]);
+
+        // Not enough room side by side anymore, so the second item is pushed down
+        engine.set_cols(1).unwrap();
+        assert_eq!(engine.grid.size(), (5, 1));
+        let positions: Vec<_> = engine
+            .items
+            .values()
+            .map(|node| (node.x, node.y, node.w, node.h))
+            .collect();
+        assert_eq!(positions, vec![(0, 0, 1, 2), (0, 2, 1, 2), (0, 4, 1, 1)]);
+
+        engine.set_cols(12).unwrap();
+        assert_eq!(engine.grid.size(), (5, 12));
+        assert_eq!(engine.items.get("0").unwrap().w, 12);
+
+        let batches = batches.lock().unwrap();
+        assert_eq!(batches.len(), 3);
+        assert!(matches!(
+            batches[0].changes.last(),
+            Some(Change::GridResize(_))
+        ));
+        assert!(matches!(
+            batches[2].changes.first(),
+            Some(Change::GridResize(_))
+        ));
+
+        // Replaying the batches reaches the same layout
+        let mut replica = GridEngine::new(4, 12);
+        replica.add_item("0".to_string(), 0, 0, 6, 2).unwrap();
+        replica.add_item("1".to_string(), 6, 0, 6, 2).unwrap();
+        replica.add_item("2".to_string(), 4, 2, 4, 1).unwrap();
+        for batch in batches.iter() {
+            replica.apply_changes(&batch.changes).unwrap();
+        }
+        assert_eq!(
+            replica.get_grid_view().hash(),
+            engine.get_grid_view().hash()
+        );
+    }
+
+    #[test]
+    fn test_set_cols_with_immovable_items() {
+        let mut engine = GridEngine::new(4, 12);
+        engine
+            .add_node(
+                Node::new("0".to_string(), 8, 0, 2, 2).with_flags(NodeFlags {
+                    locked: true,
+                    no_move: false,
+                }),
+            )
+            .unwrap();
+        engine.add_item("1".to_string(), 0, 0, 12, 1).unwrap_err();
+        engine.add_item("1".to_string(), 0, 2, 12, 1).unwrap();
+
+        let err = engine.set_cols(6).unwrap_err();
+        assert_eq!(get_out_of_bounds(&err).cols, 6);
+        assert_eq!(engine.grid.size(), (4, 12));
+
+        engine.set_cols(10).unwrap();
+        let item_0 = engine.items.get("0").unwrap();
+        assert_eq!((item_0.x, item_0.y, item_0.w), (8, 0, 2));
+        let item_1 = engine.items.get("1").unwrap();
+        assert_eq!((item_1.x, item_1.y, item_1.w), (0, 2, 10));
+
+        assert!(engine.set_cols(0).is_err());
+    }
+
+    #[test]
+    fn test_layouts() {
+        let mut engine = GridEngine::new(4, 12);
+        engine.add_item("0".to_string(), 0, 0, 6, 2).unwrap();
+        engine.add_item("1".to_string(), 6, 0, 6, 2).unwrap();
+        engine.add_layout("sm", 1).unwrap();
+        assert!(engine.add_layout("sm", 2).is_err());
+        assert!(engine.add_layout(DEFAULT_LAYOUT, 2).is_err());
+        assert_eq!(engine.get_layout_names(), vec!["default", "sm"]);
+
+        let batches = record_batches(&mut engine);
+
+        // Added and removed items show up in every layout
+        engine.add_item("2".to_string(), 0, 2, 12, 1).unwrap();
+        engine.remove_item("0").unwrap();
+
+        engine.set_layout("sm").unwrap();
+        assert_eq!(engine.get_layout(), "sm");
+        assert_eq!(engine.grid.size(), (5, 1));
+        let positions: Vec<_> = engine
+            .items
+            .values()
+            .map(|node| (node.id.as_str(), node.x, node.y, node.w))
+            .collect();
+        assert_eq!(positions, vec![("1", 0, 2, 1), ("2", 0, 4, 1)]);
+
+        // Moves only touch the active layout
+        engine.move_item("1", 0, 3).unwrap();
+        engine.set_layout(DEFAULT_LAYOUT).unwrap();
+        assert_eq!(engine.items.get("1").unwrap().y, 0);
+        assert!(engine.remove_layout(DEFAULT_LAYOUT).is_err());
+        assert!(engine.set_layout("md").is_err());
+
+        let layouts: Vec<_> = batches
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|batch| batch.layout.clone())
+            .collect();
+        assert_eq!(layouts, vec![DEFAULT_LAYOUT, DEFAULT_LAYOUT, "sm"]);
+
+        // Every layout survives the serialization
+        let serialized = engine.get_grid_view().serialized_as_str();
+        let mut deserialized = GridEngine::try_from(&serialized.into_bytes()).unwrap();
+        assert_eq!(deserialized.get_layout_names(), vec!["default", "sm"]);
+        deserialized.set_layout("sm").unwrap();
+        assert_eq!(deserialized.items.get("1").unwrap().y, 3);
+
+        engine.remove_layout("sm").unwrap();
+        assert_eq!(engine.get_layout_names(), vec!["default"]);
+    }
+
+    #[test]
+    fn test_apply_layout_changes() {
+        let mut engine = GridEngine::new(4, 12);
+        engine.add_item("0".to_string(), 0, 0, 6, 2).unwrap();
+        engine.add_layout("sm", 2).unwrap();
+
+        let mut replica = GridEngine::try_from(&Vec::<u8>::from(&engine)).unwrap();
+
+        let batches = record_batches(&mut engine);
+
+        engine.set_layout("sm").unwrap();
+        engine.move_item("0", 0, 1).unwrap();
+        engine.set_layout(DEFAULT_LAYOUT).unwrap();
+        engine.add_item("1".to_string(), 0, 2, 4, 1).unwrap();
+
+        for batch in batches.lock().unwrap().iter() {
+            replica
+                .apply_layout_changes(&batch.layout, &batch.changes)
+                .unwrap();
+        }
+        assert_eq!(replica.get_layout(), DEFAULT_LAYOUT);
+        assert_eq!(
+            replica.get_grid_view().hash(),
+            engine.get_grid_view().hash()
+        );
+        assert!(replica.apply_layout_changes("md", &[]).is_err());
+    }
+
+    #[test]
+    fn test_item_data() {
+        let mut engine = GridEngine::new(4, 4);
+        let config = serde_json::json!({ "widget": "chart", "series": [1, 2] });
+        engine
+            .add_node(Node::new("0".to_string(), 0, 0, 2, 2).with_data(config.clone()))
+            .unwrap();
+        engine.add_item("1".to_string(), 2, 0, 2, 2).unwrap();
+        engine.add_layout("sm", 2).unwrap();
+
+        let batches = record_batches(&mut engine);
+
+        // Items without data do not serialize it
+        let serialized = engine.get_grid_view().serialized_as_str();
+        assert!(serialized.contains(r#""widget":"chart""#));
+        let deserialized = GridEngine::try_from(&serialized.into_bytes()).unwrap();
+        assert_eq!(deserialized.items.get("0").unwrap().get_data(), &config);
+        assert!(deserialized.items.get("1").unwrap().get_data().is_null());
+
+        // Updates the data in every layout, without touching the positions
+        let new_config = serde_json::json!({ "widget": "table" });
+        engine.update_item_data("0", new_config.clone()).unwrap();
+        assert_eq!(engine.items.get("0").unwrap().data, new_config);
+        assert_eq!(engine.items.get("0").unwrap().x, 0);
+        engine.update_item_data("0", new_config.clone()).unwrap();
+        assert!(engine.update_item_data("2", Value::Null).is_err());
+        {
+            let batches = batches.lock().unwrap();
+            assert_eq!(batches.len(), 1);
+            assert!(matches!(
+                batches[0].changes.as_slice(),
+                [Change::UpdateData(_)]
+            ));
+        }
+
+        engine.set_layout("sm").unwrap();
+        assert_eq!(engine.items.get("0").unwrap().data, new_config);
+        engine.set_layout(DEFAULT_LAYOUT).unwrap();
+
+        // Peers get the data along with the layout, and reject batches built over another one
+        let mut peer = GridEngine::try_from(&Vec::from(&engine)).unwrap();
+        peer.update_item_data("0", serde_json::json!("stale"))
+            .unwrap();
+        let changes = batches.lock().unwrap().last().unwrap().changes.clone();
+        peer.apply_changes(&changes).unwrap_err();
+        engine
+            .update_item_data("1", serde_json::json!("note"))
+            .unwrap();
+        let changes = batches.lock().unwrap().last().unwrap().changes.clone();
+        peer.apply_changes(&changes).unwrap();
+        assert_eq!(peer.items.get("1").unwrap().data, serde_json::json!("note"));
+
+        // Only the data can change
+        let old_value = engine.items.get("1").unwrap().clone();
+        let new_value = Node {
+            x: 0,
+            ..old_value.clone()
+        };
+        let err = engine
+            .apply_changes(&[Change::UpdateData(UpdateDataChangeData {
+                old_value,
+                new_value,
+            })])
+            .unwrap_err();
+        assert_eq!(
+            get_conflict(&err).reason,
+            "Only the data of the item can be updated"
+        );
+
+        assert!(engine.undo().unwrap());
+        assert!(engine.items.get("1").unwrap().data.is_null());
+        assert!(engine.undo().unwrap());
+        assert_eq!(engine.items.get("0").unwrap().data, config);
+    }
+
+    #[test]
+    fn test_nested_grids() {
+        let mut engine = GridEngine::new(4, 12);
+        engine
+            .add_node(Node::new("tabs".to_string(), 0, 0, 6, 4).with_child_grid())
+            .unwrap();
+        engine.add_item("0".to_string(), 6, 0, 2, 2).unwrap();
+        let mut peer = GridEngine::try_from(&Vec::from(&engine)).unwrap();
+        let mut stale = GridEngine::try_from(&Vec::from(&engine)).unwrap();
+
+        let batches = record_batches(&mut engine);
+
+        // The nested grid is as wide as its node
+        engine
+            .update_child("tabs", |tabs| {
+                tabs.add_item("a".to_string(), 0, 0, 6, 1)?;
+                tabs.add_node(Node::new("panel".to_string(), 0, 1, 4, 2).with_child_grid())?;
                 Ok(())
             })
             .unwrap();
+        assert_eq!(engine.get_child("tabs").unwrap().unwrap().grid.cols(), 6);
+        assert!(engine
+            .update_child("tabs", |tabs| tabs
+                .add_item("b".to_string(), 1, 0, 6, 1)
+                .map(|_| ()))
+            .is_err());
+        engine
+            .update_child("tabs/panel", |panel| {
+                panel.add_item("b".to_string(), 0, 0, 2, 1).map(|_| ())
+            })
+            .unwrap();
+        let panel = engine.get_child("tabs/panel").unwrap().unwrap();
+        assert_eq!(panel.grid.cols(), 4);
+        assert!(panel.items.contains_key("b"));
+        assert!(matches!(
+            engine.update_child("0", |_| Ok(())),
+            Err(GridError::NoChildGrid { .. })
+        ));
+
+        // Items move between the grids as a single batch
+        engine.move_into_child("0", "tabs", 4, 3).unwrap();
+        assert!(!engine.items.contains_key("0"));
+        assert_eq!(
+            engine
+                .get_child("tabs")
+                .unwrap()
+                .unwrap()
+                .items
+                .get("0")
+                .unwrap()
+                .x,
+            4
+        );
+        assert!(engine.move_into_child("tabs", "tabs/panel", 0, 1).is_err());
+        engine.move_out_of_child("tabs/panel", "b", 8, 0).unwrap();
+        assert_eq!(engine.items.get("b").unwrap().x, 8);
+        assert!(engine
+            .get_child("tabs/panel")
+            .unwrap()
+            .unwrap()
+            .items
+            .is_empty());
+
+        // Resizing the node reflows its nested grid
+        engine.resize_item("tabs", 3, 4).unwrap();
+        let tabs = engine.get_child("tabs").unwrap().unwrap();
+        assert_eq!(tabs.grid.cols(), 3);
+        assert_eq!(tabs.items.get("a").unwrap().w, 3);
+        assert_eq!(
+            engine.get_child("tabs/panel").unwrap().unwrap().grid.cols(),
+            2
+        );
+
+        // Every operation was a single batch of the root engine, which peers can apply
+        let batches = batches.lock().unwrap().clone();
+        assert_eq!(batches.len(), 5);
+        assert!(
+            matches!(batches[1].changes.as_slice(), [Change::Nested(nested)]
+            if matches!(nested.changes.as_slice(), [Change::Nested(_)]))
+        );
+        for batch in batches.iter() {
+            peer.apply_changes(&batch.changes).unwrap();
+        }
+        assert_eq!(peer.get_grid_view().hash(), engine.get_grid_view().hash());
+
+        // A nested change that does not match its nested grid is rejected
+        let Change::Nested(mut nested) = batches[0].changes[0].clone() else {
+            panic!("Expected a nested change");
+        };
+        nested.changes.pop();
+        let err = stale.apply_changes(&[Change::Nested(nested)]).unwrap_err();
+        assert_eq!(
+            get_conflict(&err).reason,
+            "Child grid does not match the nested changes"
+        );
+
+        assert!(engine.undo().unwrap());
+        assert!(engine.undo().unwrap());
+        assert!(engine
+            .get_child("tabs/panel")
+            .unwrap()
+            .unwrap()
+            .items
+            .contains_key("b"));
+    }
+
+    #[test]
+    fn test_move_fixed_items_between_grids() {
+        let mut engine = GridEngine::new(8, 12);
+        engine
+            .add_node(
+                Node::new("header".to_string(), 0, 0, 2, 1).with_flags(NodeFlags {
+                    locked: true,
+                    no_move: false,
+                }),
+            )
+            .unwrap();
+        engine
+            .add_node(Node::new("tabs".to_string(), 0, 2, 6, 4).with_child_grid())
+            .unwrap();
+        engine
+            .update_child("tabs", |tabs| {
+                tabs.add_node(
+                    Node::new("pinned".to_string(), 1, 1, 2, 1).with_flags(NodeFlags {
+                        locked: false,
+                        no_move: true,
+                    }),
+                )
+                .map(|_| ())
+            })
+            .unwrap();
+        let view_before = engine.get_grid_view().serialized_as_str();
+
+        // Landing on the same coordinates of the other grid still moves them
+        assert!(matches!(
+            engine.move_into_child("header", "tabs", 0, 0),
+            Err(GridError::Locked { .. })
+        ));
+        assert!(matches!(
+            engine.move_out_of_child("tabs", "pinned", 1, 1),
+            Err(GridError::NoMove { .. })
+        ));
+        assert_eq!(engine.get_grid_view().serialized_as_str(), view_before);
+    }
+
+    #[test]
+    fn test_invalid_child_grids() {
+        let mut engine = GridEngine::new(4, 4);
+        let container = |items: Vec<Node>| Node {
+            child: Some(SubGrid {
+                rows: 2,
+                items: items
+                    .into_iter()
+                    .map(|item| (item.id.clone(), item))
+                    .collect(),
+            }),
+            ..Node::new("tabs".to_string(), 0, 0, 2, 2)
+        };
+        let add = |node: Node| Change::Add(AddChangeData { value: node });
+
+        // Nested items must fit in the node, without overlapping nor a path separator in their id
+        let err = engine
+            .apply_changes(&[add(container(vec![Node::new("a".to_string(), 1, 0, 2, 1)]))])
+            .unwrap_err();
+        assert!(matches!(
+            get_conflict(&err).source.as_deref(),
+            Some(GridError::OutOfBounds(_))
+        ));
+        let err = engine
+            .apply_changes(&[add(container(vec![
+                Node::new("a".to_string(), 0, 0, 2, 1),
+                Node::new("b".to_string(), 1, 0, 1, 2),
+            ]))])
+            .unwrap_err();
+        assert!(matches!(
+            get_conflict(&err).source.as_deref(),
+            Some(GridError::Overlap { .. })
+        ));
+        let err = engine
+            .apply_changes(&[add(container(vec![Node::new(
+                "a/b".to_string(),
+                0,
+                0,
+                1,
+                1,
+            )]))])
+            .unwrap_err();
+        assert!(matches!(
+            get_conflict(&err).source.as_deref(),
+            Some(GridError::InvalidId { id }) if id == "a/b"
+        ));
+
+        // Grids nested deeper are checked too
+        let nested = Node {
+            child: Some(SubGrid {
+                rows: 1,
+                items: BTreeMap::from([("b".to_string(), Node::new("b".to_string(), 1, 0, 1, 1))]),
+            }),
+            ..Node::new("a".to_string(), 0, 0, 1, 1)
+        };
+        let err = engine
+            .apply_changes(&[add(container(vec![nested]))])
+            .unwrap_err();
+        assert!(matches!(
+            get_conflict(&err).source.as_deref(),
+            Some(GridError::OutOfBounds(_))
+        ));
+        assert!(engine.items.is_empty());
+
+        // A nested grid read from a serialized grid is checked when it is used
+        engine.add_node(container(Vec::new())).unwrap();
+        let mut view = engine.get_grid_view();
+        view.items.get_mut("tabs").unwrap().child = container(vec![
+            Node::new("a".to_string(), 0, 0, 2, 2),
+            Node::new("b".to_string(), 0, 0, 1, 1),
+        ])
+        .child;
+        let mut engine = GridEngine::from(&view);
+        assert!(matches!(
+            engine.get_child("tabs"),
+            Err(GridError::Overlap { .. })
+        ));
+        assert!(matches!(
+            engine.resize_item("tabs", 3, 2),
+            Err(GridError::Overlap { .. })
+        ));
+    }
+
+    #[test]
+    fn test_undo_batch_moving_a_node_twice() {
+        let mut engine = GridEngine::new(9, 4);
+        engine.add_item("0".to_string(), 0, 5, 3, 1).unwrap();
+        engine.add_item("1".to_string(), 0, 0, 1, 1).unwrap();
+        engine.add_item("2".to_string(), 0, 6, 1, 3).unwrap();
+        engine.add_item("3".to_string(), 2, 0, 1, 2).unwrap();
+        engine.add_item("4".to_string(), 1, 0, 3, 3).unwrap();
+        let before_move = engine.get_grid_view();
+
+        // Item 0 is pushed twice, from row 5 to 6 then to 8, over the cells item 2 leaves
+        engine.move_item("2", 1, 0).unwrap();
+        engine.undo().unwrap();
+        assert_eq!(
+            engine.get_grid_view().serialized_as_str(),
+            before_move.serialized_as_str()
+        );
+        assert_eq!(engine.grid.get(6, 0).unwrap().as_deref(), Some("2"));
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut engine = GridEngine::new(4, 4);
+        engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+        engine.add_item("1".to_string(), 0, 2, 2, 2).unwrap();
+        let hash_before_move = engine.get_grid_view().hash();
+
+        let batches = record_batches(&mut engine);
+
+        // Pushes 1 down, growing the grid
+        engine.move_item("0", 0, 1).unwrap();
+        assert_eq!(engine.grid.size(), (5, 4));
+        let hash_after_move = engine.get_grid_view().hash();
+
+        assert!(engine.undo().unwrap());
+        assert_eq!(engine.get_grid_view().hash(), hash_before_move);
+        assert_eq!(engine.grid.size(), (4, 4));
+        assert_eq!(engine.items.get("1").unwrap().y, 2);
+
+        assert!(engine.redo().unwrap());
+        assert_eq!(engine.get_grid_view().hash(), hash_after_move);
+        assert!(!engine.redo().unwrap());
+
+        // Undo is a regular batch, so peers follow along
+        {
+            let batches = batches.lock().unwrap();
+            assert_eq!(batches.len(), 3);
+            assert_eq!(batches[1].hash_before, hash_after_move);
+            assert_eq!(batches[1].hash_after, hash_before_move);
+        }
+
+        assert!(engine.undo().unwrap());
+        assert!(engine.undo().unwrap());
+        assert!(engine.undo().unwrap());
+        assert!(engine.items.is_empty());
+        assert!(!engine.undo().unwrap());
+
+        // A new batch drops what could be redone
+        assert!(engine.redo().unwrap());
+        engine.add_item("2".to_string(), 2, 0, 1, 1).unwrap();
+        assert!(!engine.can_redo());
+    }
+
+    #[test]
+    fn test_history_depth() {
+        let mut engine = GridEngine::new(4, 4);
+        engine.set_history_depth(2);
+        for id in 0..4 {
+            engine.add_item(id.to_string(), id, 0, 1, 1).unwrap();
+        }
+
+        assert!(engine.undo().unwrap());
+        assert!(engine.undo().unwrap());
+        assert!(!engine.undo().unwrap());
+        assert_eq!(engine.items.len(), 2);
+
+        // Changes received from peers are not undone
+        engine
+            .apply_changes(&[Change::Add(AddChangeData {
+                value: Node::new("4".to_string(), 3, 3, 1, 1),
+            })])
+            .unwrap();
+        assert!(!engine.can_undo());
+        assert!(engine.can_redo());
+
+        engine.set_history_depth(0);
+        engine.add_item("5".to_string(), 2, 2, 1, 1).unwrap();
+        assert!(!engine.can_undo());
+    }
+
+    #[test]
+    fn test_transaction() {
+        let mut engine = GridEngine::new(4, 4);
+        engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+
+        let batches = record_batches(&mut engine);
+        let hash_before = engine.get_grid_view().hash();
+
+        engine.begin_transaction().unwrap();
+        assert!(engine.begin_transaction().is_err());
+        engine.add_item("1".to_string(), 2, 0, 2, 2).unwrap();
+        // Operations see the ones before them in the transaction
+        assert!(engine.add_item("1".to_string(), 0, 2, 1, 1).is_err());
+        engine.move_item("1", 0, 0).unwrap();
+        engine.remove_item("0").unwrap();
+        // A failed operation is discarded without dropping the others
+        assert!(engine.move_item("1", 4, 0).is_err());
+        assert!(engine.set_cols(2).is_err());
+
+        assert!(batches.lock().unwrap().is_empty());
+        assert_eq!(engine.get_grid_view().hash(), hash_before);
+        engine.commit().unwrap();
+
+        {
+            let batches = batches.lock().unwrap();
+            assert_eq!(batches.len(), 1);
+            assert_eq!(batches[0].hash_before, hash_before);
+            assert_eq!(batches[0].hash_after, engine.get_grid_view().hash());
+        }
+        assert_eq!(engine.items.len(), 1);
+        let item_1 = engine.items.get("1").unwrap();
+        assert_eq!((item_1.x, item_1.y), (0, 0));
+        assert_eq!(engine.grid.get(0, 2), Some(&None));
+
+        // The whole transaction is a single step of the history
+        assert!(engine.undo().unwrap());
+        assert_eq!(engine.get_grid_view().hash(), hash_before);
+        assert!(engine.commit().is_err());
+    }
+
+    #[test]
+    fn test_transaction_rollback() {
+        let mut engine = GridEngine::new(4, 4);
+        engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+        let hash_before = engine.get_grid_view().hash();
+
+        engine.begin_transaction().unwrap();
+        engine.add_item("1".to_string(), 0, 0, 2, 4).unwrap();
+        engine.rollback().unwrap();
+        assert!(engine.rollback().is_err());
+        assert_eq!(engine.get_grid_view().hash(), hash_before);
+
+        let result = engine.transaction(|engine| {
+            engine.add_item("1".to_string(), 2, 0, 2, 2)?;
+            engine.move_item("2", 0, 0)
+        });
+        assert!(result.is_err());
+        assert!(!engine.in_transaction());
+        assert_eq!(engine.get_grid_view().hash(), hash_before);
+
+        let id = engine
+            .transaction(|engine| engine.add_item_auto("1".to_string(), 2, 2))
+            .unwrap();
+        assert_eq!(id, "1");
+        let item_1 = engine.items.get("1").unwrap();
+        assert_eq!((item_1.x, item_1.y), (2, 0));
+    }
+
+    #[test]
+    fn test_preview() {
+        let mut engine = GridEngine::new(4, 4);
+        engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+        engine.add_item("1".to_string(), 0, 2, 2, 1).unwrap();
+
+        let triggered = Arc::new(Mutex::new(false));
+        let triggered_clone = Arc::clone(&triggered);
+        engine.events.add_listener(
+            EventName::BatchChange,
+            Box::new(move |_, _| {
+                *triggered_clone.lock().unwrap() = true;
+            }),
+        );
+        let hash_before = engine.get_grid_view().hash();
+
+        let preview = engine.preview_move_item("0", 0, 1).unwrap();
+        let moved: Vec<_> = preview
+            .changes
+            .iter()
+            .filter_map(|change| match change {
+                Change::Move(data) => Some((data.new_value.id.as_str(), data.new_value.y)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(moved, vec![("1", 3), ("0", 1)]);
+        assert_eq!(preview.grid_view.items.get("1").unwrap().y, 3);
+
+        let preview = engine
+            .preview_add_item("2".to_string(), 2, 0, 2, 4)
+            .unwrap();
+        assert_eq!(preview.changes.len(), 1);
+        assert_eq!(preview.grid_view.items.len(), 3);
+        assert!(engine
+            .preview_add_item("0".to_string(), 2, 0, 1, 1)
+            .is_err());
+        assert!(engine.preview_move_item("0", 3, 0).is_err());
+
+        assert_eq!(engine.get_grid_view().hash(), hash_before);
+        assert!(!*triggered.lock().unwrap());
+        assert!(!engine.can_redo());
+
+        // Applying the previewed changes gives the previewed grid
+        let preview = engine.preview_move_item("0", 0, 1).unwrap();
+        engine.apply_changes(&preview.changes).unwrap();
+        assert_eq!(engine.get_grid_view().hash(), preview.grid_view.hash());
+    }
+
+    #[test]
+    fn test_preview_within_transaction() {
+        let mut engine = GridEngine::new(4, 4);
+        engine.begin_transaction().unwrap();
+        engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+
+        let preview = engine
+            .preview_add_item("1".to_string(), 0, 0, 1, 1)
+            .unwrap();
+        assert_eq!(preview.changes.len(), 2);
+        assert_eq!(preview.grid_view.items.len(), 2);
+
+        engine.commit().unwrap();
+        assert_eq!(engine.items.len(), 1);
+    }
+
+    #[test]
+    fn test_error_variants() {
+        fn assert_send_sync<T: Send + Sync + std::error::Error>() {}
+        assert_send_sync::<GridError>();
+
+        let mut engine = GridEngine::new(4, 4);
+        engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+
+        let err = engine.remove_item("1").unwrap_err();
+        assert!(matches!(&err, GridError::ItemNotFound { id } if id == "1"));
+        assert_eq!(err.get_message(), "Item 1 not found");
+
+        let err = engine.add_item("0".to_string(), 2, 2, 1, 1).unwrap_err();
+        assert!(matches!(err, GridError::DuplicateId { .. }));
+        let err = engine.add_item("1".to_string(), 2, 2, 0, 1).unwrap_err();
+        assert!(matches!(err, GridError::InvalidSize { w: 0, h: 1 }));
+
+        let err = GridEngine::try_from(&b"{".to_vec()).unwrap_err();
+        assert!(matches!(err, GridError::Deserialize(_)));
+        assert!(std::error::Error::source(&err).is_some());
+        let err = EventValue::try_from(vec![0xff]).unwrap_err();
+        assert!(matches!(err, GridError::Encoding(_)));
+
+        let err = engine
+            .apply_changes(&[Change::Move(MoveChangeData {
+                old_value: Node::new("0".to_string(), 0, 0, 2, 2),
+                new_value: Node::new("0".to_string(), 3, 0, 2, 2),
+            })])
+            .unwrap_err();
+        assert_eq!(
+            err.get_message(),
+            "Change 0 on item 0 can not be applied: Out of bounds: \
+             Rectangle X:3,Y:0,W:2,H:2 does not fit in the 4x4 grid"
+        );
     }
 
     #[test]
@@ -617,7 +4201,7 @@ mod tests {
         // Asserts that does not collide with self
         assert_eq!(
             engine.will_collides_with(
-                &engine.items.get(&item_0_id).unwrap(),
+                engine.items.get(&item_0_id).unwrap(),
                 0,
                 0,
                 &engine.grid.clone()
@@ -628,7 +4212,7 @@ mod tests {
         // Asserts that does not collide with empty position
         assert_eq!(
             engine.will_collides_with(
-                &engine.items.get(&item_0_id).unwrap(),
+                engine.items.get(&item_0_id).unwrap(),
                 2,
                 2,
                 &engine.grid.clone()
@@ -642,7 +4226,7 @@ mod tests {
         // Full collision
         assert_eq!(
             engine.will_collides_with(
-                &engine.items.get(&item_0_id).unwrap(),
+                engine.items.get(&item_0_id).unwrap(),
                 1,
                 2,
                 &engine.grid.clone()
@@ -653,7 +4237,7 @@ mod tests {
         // Partial collision
         assert_eq!(
             engine.will_collides_with(
-                &engine.items.get(&item_0_id).unwrap(),
+                engine.items.get(&item_0_id).unwrap(),
                 1,
                 1,
                 &engine.grid.clone()
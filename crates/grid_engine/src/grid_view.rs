@@ -1,17 +1,23 @@
 use std::collections::BTreeMap;
 
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
-
-use grid::Grid;
 use serde::{Deserialize, Serialize};
 
-use crate::grid_engine::{GridEngine, Node};
+use crate::diff::{self, ViewDiff};
+use crate::error::GridError;
+use crate::free_space::{self, FitMap};
+use crate::grid_engine::{default_layout, is_default_layout, GridEngine, Layout, Node};
+use crate::hash;
+use crate::query::{self, Neighbors, Rect};
+use crate::storage::{CellStorage, Cells};
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct GridView {
-    pub(crate) grid: Grid<Option<String>>,
+    pub(crate) grid: Cells,
     pub(crate) items: BTreeMap<String, Node>,
+    #[serde(default = "default_layout", skip_serializing_if = "is_default_layout")]
+    pub(crate) layout: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) layouts: BTreeMap<String, Layout>,
 }
 
 impl GridView {
@@ -19,9 +25,16 @@ impl GridView {
         GridView {
             grid: grid_engine.grid.clone(),
             items: grid_engine.items.clone(),
+            layout: grid_engine.layout.clone(),
+            layouts: grid_engine.layouts.clone(),
         }
     }
 
+    /// Name of the layout the grid and nodes belong to
+    pub fn get_layout(&self) -> &str {
+        &self.layout
+    }
+
     /// Get the nodes sorted by id
     pub fn get_nodes(&self) -> Vec<Node> {
         let mut cloned: Vec<Node> = self.items.values().cloned().collect();
@@ -30,6 +43,56 @@ impl GridView {
         cloned
     }
 
+    /// Gets the item covering the cell, if any
+    pub fn item_at(&self, x: usize, y: usize) -> Option<&Node> {
+        query::item_at(&self.grid, &self.items, x, y)
+    }
+
+    /// Gets the items intersecting the rectangle, sorted by id
+    pub fn items_in_rect(&self, x: usize, y: usize, w: usize, h: usize) -> Vec<&Node> {
+        query::items_in_rect(&self.items, x, y, w, h)
+    }
+
+    /// Gets the nearest item on each side of the item
+    pub fn neighbors(&self, id: &str) -> Result<Neighbors, GridError> {
+        query::neighbors(&self.items, id)
+    }
+
+    /// Gets the smallest rectangle containing every item, `None` if there is no item
+    pub fn bounding_box(&self) -> Option<Rect> {
+        query::bounding_box(&self.items)
+    }
+
+    /// Gets the ratio of the cells covered by items, from 0 to 1
+    pub fn occupancy(&self) -> f64 {
+        query::occupancy(&self.grid, &self.items)
+    }
+
+    /// Gets the empty rectangles that can not grow in any direction, largest first
+    pub fn maximal_empty_rects(&self) -> Vec<Rect> {
+        free_space::maximal_empty_rects(&self.grid)
+    }
+
+    /// Gets the largest empty rectangle, `None` if the grid is full
+    pub fn largest_empty_rect(&self) -> Option<Rect> {
+        self.maximal_empty_rects().into_iter().next()
+    }
+
+    /// Gets the largest sizes that fit with their top-left corner on each cell
+    pub fn fit_map(&self) -> FitMap {
+        free_space::fit_map(&self.grid)
+    }
+
+    /// Whether a `w`x`h` item can be placed somewhere without pushing other items
+    pub fn can_fit(&self, w: usize, h: usize) -> bool {
+        free_space::can_fit(&self.grid, w, h)
+    }
+
+    /// Gets the changes turning this grid into `to`, see `diff::diff`
+    pub fn diff(&self, to: &GridView) -> Result<ViewDiff, GridError> {
+        diff::diff(self, to)
+    }
+
     /// Prints answer of get_grid_formatted
     pub fn print_grid(&self) {
         println!("{}", self.get_grid_formatted(1));
@@ -42,27 +105,28 @@ impl GridView {
         for i in 0..self.grid.cols() {
             grid_str.push_str(&format!(" {} ", i));
         }
-        grid_str.push_str("\n");
-
-        self.grid
-            .iter_rows()
-            .enumerate()
-            .for_each(|(row_number, row)| {
-                row.enumerate().for_each(|(index, cell)| {
-                    if index == 0 {
-                        grid_str.push_str(&format!("{:0>2}", row_number));
+        grid_str.push('\n');
+
+        (0..self.grid.rows()).for_each(|row_number| {
+            (0..self.grid.cols()).for_each(|index| {
+                let cell = self
+                    .grid
+                    .get(row_number, index)
+                    .expect("Cell is in the grid");
+                if index == 0 {
+                    grid_str.push_str(&format!("{:0>2}", row_number));
+                }
+                match cell {
+                    Some(item) => {
+                        grid_str.push_str(&format!("[{}]", item));
                     }
-                    return match cell {
-                        Some(item) => {
-                            grid_str.push_str(&format!("[{}]", item));
-                        }
-                        None => {
-                            grid_str.push_str(&format!("[{}]", " ".repeat(cell_space as usize)));
-                        }
-                    };
-                });
-                grid_str.push_str("\n");
+                    None => {
+                        grid_str.push_str(&format!("[{}]", " ".repeat(cell_space as usize)));
+                    }
+                };
             });
+            grid_str.push('\n');
+        });
 
         grid_str
     }
@@ -71,11 +135,19 @@ impl GridView {
         serde_json::to_string(self).expect("Failed to serialize GridEngine")
     }
 
+    /// Stable hash of the grid and of every layout, computed the same way on every platform.
+    /// See the `hash` module for the algorithm.
     pub fn hash(&self) -> String {
-        // Get the grid serialized as str and return a hash of it
-        let serialized = self.serialized_as_str();
-        let mut hasher = DefaultHasher::new();
-        serialized.hash(&mut hasher);
-        hasher.finish().to_string()
+        hash::format_hash(self.compute_hash())
+    }
+
+    /// Computes the hash from scratch, over every node of every layout
+    pub(crate) fn compute_hash(&self) -> u64 {
+        self.layouts.iter().fold(
+            hash::layout_hash(&self.layout, self.grid.size(), &self.items),
+            |hash, (name, layout)| {
+                hash ^ hash::layout_hash(name, layout.grid.size(), &layout.items)
+            },
+        )
     }
 }
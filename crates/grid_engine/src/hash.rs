@@ -0,0 +1,149 @@
+//! Stable hash of a grid, that peers compare to know if they hold the same grid.
+//!
+//! The hash is the XOR of one entry for the size of every layout, the active one included,
+//! and one entry for every node of each layout. Each entry is the 64 bit FNV-1a hash of:
+//! - `{layout}\0{rows}x{cols}` for the size of a layout
+//! - `{layout}\0{node}` for a node, where `{node}` is its JSON serialization, with the fields in
+//!   declaration order, empty options omitted, object keys sorted and no whitespace
+//!
+//! It is formatted as 16 lowercase hexadecimal digits. As the entries are combined with XOR,
+//! the hash does not depend on their order, and a change only replaces the entries it touches.
+
+use std::collections::BTreeMap;
+
+use crate::grid_engine::Node;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// 64 bit FNV-1a hash of the bytes
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+pub(crate) fn size_entry(layout: &str, rows: usize, cols: usize) -> u64 {
+    fnv1a(format!("{}\0{}x{}", layout, rows, cols).as_bytes())
+}
+
+pub(crate) fn node_entry(layout: &str, node: &Node) -> u64 {
+    let serialized = serde_json::to_string(node).expect("Failed to serialize Node");
+    fnv1a(format!("{}\0{}", layout, serialized).as_bytes())
+}
+
+/// Combines the entries of the size and of every node of a layout
+pub(crate) fn layout_hash(
+    layout: &str,
+    (rows, cols): (usize, usize),
+    items: &BTreeMap<String, Node>,
+) -> u64 {
+    items
+        .values()
+        .fold(size_entry(layout, rows, cols), |hash, node| {
+            hash ^ node_entry(layout, node)
+        })
+}
+
+pub fn format_hash(hash: u64) -> String {
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid_engine::{Change, GridEngine, GridResizeChangeData, Node, NodeFlags};
+    use crate::test_utils::random_generator;
+
+    use super::*;
+
+    #[test]
+    fn test_stable_hash() {
+        // Reference vectors of the 64 bit FNV-1a
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+
+        // Pinned, any change of them breaks the sync with peers running another version
+        let mut engine = GridEngine::new(4, 12);
+        assert_eq!(engine.get_grid_view().hash(), "61d2e65d10adadf5");
+        engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+        assert_eq!(engine.get_grid_view().hash(), "2c48bf10b9282f9a");
+        engine
+            .add_node(
+                Node::new("1".to_string(), 2, 0, 4, 2)
+                    .with_flags(NodeFlags {
+                        locked: true,
+                        no_move: false,
+                    })
+                    .with_data(serde_json::json!({ "widget": "chart", "color": "red" })),
+            )
+            .unwrap();
+        assert_eq!(engine.get_grid_view().hash(), "8cddda76f382b734");
+        engine.add_layout("sm", 6).unwrap();
+        assert_eq!(engine.get_grid_view().hash(), "5492ea5f6fdea8e1");
+
+        // The order the items were added in does not matter
+        let mut other = GridEngine::new(4, 12);
+        other
+            .add_node(engine.items.get("1").unwrap().clone())
+            .unwrap();
+        other.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+        other.add_layout("sm", 6).unwrap();
+        assert_eq!(other.get_grid_view().hash(), engine.get_grid_view().hash());
+    }
+
+    #[test]
+    fn test_incremental_hash() {
+        let mut engine = GridEngine::new(6, 8);
+        engine
+            .add_node(Node::new("tabs".to_string(), 0, 0, 4, 2).with_child_grid())
+            .unwrap();
+        engine.add_layout("sm", 3).unwrap();
+        assert_eq!(engine.get_hash(), engine.get_grid_view().hash());
+
+        // Deterministic pseudo random operations, failed ones included
+        let mut random = random_generator(42);
+        for step in 0..300 {
+            let id = random(8).to_string();
+            let _ = match random(10) {
+                0 | 1 => engine
+                    .add_item(id, random(8), random(8), 1 + random(3), 1 + random(3))
+                    .map(|_| ()),
+                2 => engine.move_item(&id, random(8), random(8)),
+                3 => engine.resize_item(&id, 1 + random(4), 1 + random(3)),
+                4 => engine.remove_item(&id),
+                5 => engine.update_item_data(&id, serde_json::json!(step)),
+                6 => engine.update_child("tabs", |tabs| {
+                    tabs.add_item(format!("child{}", step), random(4), random(4), 1, 1)
+                        .map(|_| ())
+                }),
+                7 => engine.set_layout(["default", "sm"][random(2)]),
+                8 => engine.set_cols(4 + random(8)),
+                _ => engine.undo().map(|_| ()),
+            };
+            assert_eq!(
+                engine.get_hash(),
+                engine.get_grid_view().hash(),
+                "Running hash diverged at step {}",
+                step
+            );
+        }
+
+        // Restored after a failed batch
+        let hash = engine.get_hash();
+        let err = engine.apply_changes(&[Change::GridResize(GridResizeChangeData {
+            old_rows: 0,
+            old_cols: 0,
+            new_rows: 1,
+            new_cols: 1,
+        })]);
+        assert!(err.is_err());
+        assert_eq!(engine.get_hash(), hash);
+
+        let deserialized = GridEngine::try_from(&Vec::from(&engine)).unwrap();
+        assert_eq!(deserialized.get_hash(), hash);
+        let deserialized: GridEngine =
+            serde_json::from_str(&engine.get_grid_view().serialized_as_str()).unwrap();
+        assert_eq!(deserialized.get_hash(), hash);
+    }
+}
@@ -1,4 +1,12 @@
 pub mod grid_engine;
+pub mod batch;
+pub mod diff;
 mod engine_events;
-mod error;
-pub mod grid_view;
\ No newline at end of file
+pub mod error;
+pub mod free_space;
+pub mod grid_view;
+pub mod hash;
+pub mod query;
+pub mod storage;
+#[cfg(test)]
+mod test_utils;
\ No newline at end of file
@@ -5,8 +5,11 @@ use grid_engine::grid_engine::{EventName, GridEngine};
 enum Interaction {
     AddItem(String, usize, usize, usize, usize),
     MoveItem(String, usize, usize),
+    ResizeItem(String, usize, usize),
     RemoveItem(String),
-    InvalidInteraction(String),
+    Undo,
+    Redo,
+    Invalid(String),
 }
 
 impl Interaction {
@@ -61,30 +64,27 @@ impl Interaction {
                     .expect("Expect y to be number");
                 Interaction::MoveItem(id.to_string(), x, y)
             }
-            _ => Interaction::InvalidInteraction(input.to_string()),
-        }
-    }
-}
-
-#[derive(Clone, Debug)]
-struct GridContent {
-    name: String,
-}
-
-impl Default for GridContent {
-    fn default() -> Self {
-        GridContent {
-            name: "0".to_string(),
+            "rs" => {
+                let id = parts.next().expect("Expect ID");
+                let w = parts
+                    .next()
+                    .expect("Expect W")
+                    .parse()
+                    .expect("Expect w to be number");
+                let h = parts
+                    .next()
+                    .expect("Expect H")
+                    .parse()
+                    .expect("Expect h to be number");
+                Interaction::ResizeItem(id.to_string(), w, h)
+            }
+            "undo" => Interaction::Undo,
+            "redo" => Interaction::Redo,
+            _ => Interaction::Invalid(input.to_string()),
         }
     }
 }
 
-impl std::fmt::Display for GridContent {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.name)
-    }
-}
-
 fn print_grid(grid: &mut GridEngine) {
     // print!("\x1B[2J\x1B[1;1H");
     println!("Printing the grid");
@@ -105,7 +105,19 @@ fn handle_interaction(grid: &mut GridEngine, interaction: Interaction) {
             println!("Moving item {} to ({}, {})", &id, x, y);
             grid.move_item(&id, x, y).unwrap();
         }
-        Interaction::InvalidInteraction(instruction) => {
+        Interaction::ResizeItem(id, w, h) => {
+            println!("Resizing item {} to {}x{}", &id, w, h);
+            grid.resize_item(&id, w, h).unwrap();
+        }
+        Interaction::Undo => {
+            println!("Undoing the last change");
+            grid.undo().unwrap();
+        }
+        Interaction::Redo => {
+            println!("Redoing the last undone change");
+            grid.redo().unwrap();
+        }
+        Interaction::Invalid(instruction) => {
             println!("Invalid interaction: {}", instruction);
         }
     }
@@ -135,12 +147,10 @@ fn scripted_mode() {
 
     grid.events.add_listener(
         EventName::BatchChange,
-        Box::new(|grid, event| match event {
-            grid_engine::grid_engine::EventValue::BatchChange(events) => {
-                println!("BatchChange: {:#?}", events);
-                grid.print_grid();
-            }
-            _ => {}
+        Box::new(|grid, event| {
+            let grid_engine::grid_engine::EventValue::BatchChange(events) = event;
+            println!("BatchChange: {:#?}", events);
+            grid.print_grid();
         }),
     );
 
@@ -161,6 +171,9 @@ fn scripted_mode() {
         "mv c 3 2",
         "mv c 4 10",
         "mv c 4 6",
+        "rs c 3 3",
+        "undo",
+        "redo",
         // "mv d 1 1",
         // "mv c 4 6", // Bug
     ];
@@ -0,0 +1,236 @@
+//! Read-only spatial queries over the cells and nodes of a grid, shared by `GridView` and
+//! `GridEngine`.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use tsify_next::Tsify;
+
+use crate::error::GridError;
+use crate::grid_engine::Node;
+use crate::storage::{CellStorage, Cells};
+
+/// Rectangle of cells
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Hash, Tsify)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
+}
+
+/// Nearest item on each side of an item, by id
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Hash, Tsify)]
+pub struct Neighbors {
+    pub left: Option<String>,
+    pub right: Option<String>,
+    pub up: Option<String>,
+    pub down: Option<String>,
+}
+
+pub(crate) fn item_at<'a>(
+    grid: &Cells,
+    items: &'a BTreeMap<String, Node>,
+    x: usize,
+    y: usize,
+) -> Option<&'a Node> {
+    match grid.get(y, x) {
+        Some(Some(id)) => items.get(id),
+        _ => None,
+    }
+}
+
+pub(crate) fn items_in_rect(
+    items: &BTreeMap<String, Node>,
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+) -> Vec<&Node> {
+    items
+        .values()
+        .filter(|node| {
+            node.x < x.saturating_add(w)
+                && x < node.x + node.w
+                && node.y < y.saturating_add(h)
+                && y < node.y + node.h
+        })
+        .collect()
+}
+
+/// For each side, the item whose span on the other axis overlaps the item, with the smallest gap
+/// to it. Ties go to the item closest to the top, or to the left.
+pub(crate) fn neighbors(items: &BTreeMap<String, Node>, id: &str) -> Result<Neighbors, GridError> {
+    let node = match items.get(id) {
+        Some(node) => node,
+        None => Err(GridError::ItemNotFound { id: id.to_string() })?,
+    };
+
+    let overlaps_rows = |other: &&Node| other.y < node.y + node.h && node.y < other.y + other.h;
+    let overlaps_cols = |other: &&Node| other.x < node.x + node.w && node.x < other.x + other.w;
+    let others = || items.values().filter(|other| other.id != node.id);
+    let nearest = |candidates: Vec<(usize, usize, &Node)>| {
+        candidates
+            .into_iter()
+            .min_by_key(|(gap, position, _)| (*gap, *position))
+            .map(|(_, _, other)| other.id.clone())
+    };
+
+    Ok(Neighbors {
+        left: nearest(
+            others()
+                .filter(overlaps_rows)
+                .filter(|other| other.x + other.w <= node.x)
+                .map(|other| (node.x - other.x - other.w, other.y, other))
+                .collect(),
+        ),
+        right: nearest(
+            others()
+                .filter(overlaps_rows)
+                .filter(|other| other.x >= node.x + node.w)
+                .map(|other| (other.x - node.x - node.w, other.y, other))
+                .collect(),
+        ),
+        up: nearest(
+            others()
+                .filter(overlaps_cols)
+                .filter(|other| other.y + other.h <= node.y)
+                .map(|other| (node.y - other.y - other.h, other.x, other))
+                .collect(),
+        ),
+        down: nearest(
+            others()
+                .filter(overlaps_cols)
+                .filter(|other| other.y >= node.y + node.h)
+                .map(|other| (other.y - node.y - node.h, other.x, other))
+                .collect(),
+        ),
+    })
+}
+
+pub(crate) fn bounding_box(items: &BTreeMap<String, Node>) -> Option<Rect> {
+    let (x, y, right, bottom) = items.values().fold(None, |bounds, node| {
+        let (x, y, right, bottom) = bounds.unwrap_or((node.x, node.y, 0, 0));
+        Some((
+            x.min(node.x),
+            y.min(node.y),
+            right.max(node.x + node.w),
+            bottom.max(node.y + node.h),
+        ))
+    })?;
+    Some(Rect {
+        x,
+        y,
+        w: right - x,
+        h: bottom - y,
+    })
+}
+
+/// Ratio of the cells covered by items, 0 for an empty grid
+pub(crate) fn occupancy(grid: &Cells, items: &BTreeMap<String, Node>) -> f64 {
+    let (rows, cols) = grid.size();
+    if rows == 0 || cols == 0 {
+        return 0.0;
+    }
+    let occupied: usize = items.values().map(|node| node.w * node.h).sum();
+    occupied as f64 / (rows * cols) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid_engine::GridEngine;
+
+    use super::*;
+
+    #[test]
+    fn test_spatial_queries() {
+        let mut engine = GridEngine::new(6, 8);
+        assert_eq!(engine.bounding_box(), None);
+        assert_eq!(engine.occupancy(), 0.0);
+
+        // 0 0 . . 1 1 . .
+        // 0 0 . . 1 1 . .
+        // . 2 2 2 . . . 3
+        // . . . . . . . .
+        engine.add_item("0".to_string(), 0, 0, 2, 2).unwrap();
+        engine.add_item("1".to_string(), 4, 0, 2, 2).unwrap();
+        engine.add_item("2".to_string(), 1, 2, 3, 1).unwrap();
+        engine.add_item("3".to_string(), 7, 2, 1, 1).unwrap();
+        let grid_view = engine.get_grid_view();
+
+        assert_eq!(
+            grid_view.item_at(1, 1).map(|node| node.id.as_str()),
+            Some("0")
+        );
+        assert_eq!(
+            grid_view.item_at(3, 2).map(|node| node.id.as_str()),
+            Some("2")
+        );
+        assert_eq!(grid_view.item_at(2, 0), None);
+        assert_eq!(grid_view.item_at(20, 0), None);
+
+        let ids = |nodes: Vec<&Node>| -> Vec<String> {
+            nodes.into_iter().map(|node| node.id.clone()).collect()
+        };
+        assert_eq!(
+            ids(grid_view.items_in_rect(1, 1, 4, 2)),
+            vec!["0", "1", "2"]
+        );
+        assert_eq!(
+            ids(grid_view.items_in_rect(2, 0, 2, 2)),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            ids(grid_view.items_in_rect(0, 0, 2, 0)),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            ids(grid_view.items_in_rect(4, 2, usize::MAX, usize::MAX)),
+            vec!["3"]
+        );
+        assert_eq!(
+            ids(grid_view.items_in_rect(usize::MAX, 0, usize::MAX, 1)),
+            Vec::<String>::new()
+        );
+
+        assert_eq!(
+            grid_view.neighbors("0").unwrap(),
+            Neighbors {
+                left: None,
+                right: Some("1".to_string()),
+                up: None,
+                down: Some("2".to_string()),
+            }
+        );
+        assert_eq!(
+            grid_view.neighbors("2").unwrap(),
+            Neighbors {
+                left: None,
+                right: Some("3".to_string()),
+                up: Some("0".to_string()),
+                down: None,
+            }
+        );
+        assert!(matches!(
+            grid_view.neighbors("4"),
+            Err(GridError::ItemNotFound { .. })
+        ));
+
+        assert_eq!(
+            grid_view.bounding_box(),
+            Some(Rect {
+                x: 0,
+                y: 0,
+                w: 8,
+                h: 3
+            })
+        );
+        assert_eq!(grid_view.occupancy(), 12.0 / 48.0);
+
+        // The engine answers the same
+        assert_eq!(engine.item_at(5, 1).map(|node| node.id.as_str()), Some("1"));
+        assert_eq!(engine.neighbors("1").unwrap().left, Some("0".to_string()));
+        assert_eq!(engine.bounding_box(), grid_view.bounding_box());
+        assert_eq!(engine.occupancy(), grid_view.occupancy());
+    }
+}
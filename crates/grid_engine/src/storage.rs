@@ -0,0 +1,674 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use grid::{Grid, Order};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Stores the id of the item on each cell of a grid
+pub trait CellStorage: Clone {
+    fn size(&self) -> (usize, usize);
+
+    fn rows(&self) -> usize {
+        self.size().0
+    }
+
+    fn cols(&self) -> usize {
+        self.size().1
+    }
+
+    /// Gets the cell, or `None` when it is outside of the grid
+    fn get(&self, y: usize, x: usize) -> Option<&Option<String>>;
+
+    /// Sets the cell, returning false when it is outside of the grid
+    fn set(&mut self, y: usize, x: usize, id: Option<&str>) -> bool;
+
+    /// Adds or drops rows and columns at the end. A grid without rows keeps its columns, so it
+    /// can grow rows later, but a grid without columns is 0x0.
+    fn resize(&mut self, rows: usize, cols: usize);
+
+    /// Ids of the items on the rectangle, other than `id`, ordered by their first cell going
+    /// column by column. Cells outside of the grid are ignored.
+    fn collisions(&self, x: usize, y: usize, w: usize, h: usize, id: &str) -> Vec<String> {
+        let mut collisions: Vec<String> = Vec::new();
+        for x in x..x + w {
+            for y in y..y + h {
+                if let Some(Some(cell)) = self.get(y, x) {
+                    if cell != id && !collisions.contains(cell) {
+                        collisions.push(cell.clone());
+                    }
+                }
+            }
+        }
+        collisions
+    }
+}
+
+/// Stores every cell, with its own copy of the id of its item
+#[derive(Clone, Debug)]
+pub struct DenseGrid {
+    cells: Grid<Option<String>>,
+    /// Columns of the grid, kept when it has no rows, as `cells` is then 0x0
+    cols: usize,
+}
+
+impl DenseGrid {
+    pub fn new(rows: usize, cols: usize) -> DenseGrid {
+        DenseGrid {
+            cells: Grid::new(rows, cols),
+            cols,
+        }
+    }
+}
+
+impl CellStorage for DenseGrid {
+    fn size(&self) -> (usize, usize) {
+        (self.cells.rows(), self.cols)
+    }
+
+    fn get(&self, y: usize, x: usize) -> Option<&Option<String>> {
+        self.cells.get(y, x)
+    }
+
+    fn set(&mut self, y: usize, x: usize, id: Option<&str>) -> bool {
+        match self.cells.get_mut(y, x) {
+            Some(cell) => {
+                *cell = id.map(str::to_string);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn resize(&mut self, rows: usize, cols: usize) {
+        let (old_rows, old_cols) = self.size();
+        self.cols = cols;
+
+        // `Grid` can not push to a 0x0 grid nor pop its last row or column, so it is rebuilt.
+        // Either grid has no cells, so there are none to copy over.
+        if [old_rows, old_cols, rows, cols].contains(&0) {
+            self.cells = Grid::new(rows, cols);
+            return;
+        }
+
+        let grid = &mut self.cells;
+        while grid.cols() < cols {
+            grid.push_col(vec![None; grid.rows()]);
+        }
+        while grid.cols() > cols {
+            grid.pop_col();
+        }
+        while grid.rows() < rows {
+            grid.push_row(vec![None; grid.cols()]);
+        }
+        while grid.rows() > rows {
+            grid.pop_row();
+        }
+    }
+}
+
+/// Serialized form of a dense grid, the one of `Grid`, along with the columns of a grid without
+/// rows
+#[derive(Serialize, Deserialize)]
+struct SerializedDenseGrid<'a> {
+    cols: usize,
+    data: Cow<'a, [Option<String>]>,
+    #[serde(default)]
+    order: Order,
+}
+
+impl Serialize for DenseGrid {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedDenseGrid {
+            cols: self.cols,
+            data: Cow::Borrowed(self.cells.flatten()),
+            order: self.cells.order(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DenseGrid {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let SerializedDenseGrid { cols, data, order } =
+            SerializedDenseGrid::deserialize(deserializer)?;
+        let fits = match cols {
+            0 => data.is_empty(),
+            cols => data.len() % cols == 0,
+        };
+        if !fits {
+            return Err(serde::de::Error::custom(format!(
+                "Expected cells for {} columns, but got {}",
+                cols,
+                data.len()
+            )));
+        }
+
+        Ok(DenseGrid {
+            cells: Grid::from_vec_with_order(data.into_owned(), cols, order),
+            cols,
+        })
+    }
+}
+
+/// Cells of a row covered by the same item, from `start` to `end` excluded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Run {
+    start: usize,
+    end: usize,
+    /// Index of the interned id of the item
+    index: u32,
+}
+
+/// Position of the run covering the column, if any
+fn find_run(runs: &[Run], x: usize) -> Option<usize> {
+    let position = runs.partition_point(|run| run.start <= x).checked_sub(1)?;
+    (x < runs[position].end).then_some(position)
+}
+
+/// Adds the empty cell of the column to the runs, merged with the runs of the same item next to
+/// it
+fn insert_cell(runs: &mut Vec<Run>, x: usize, index: u32) {
+    let position = runs.partition_point(|run| run.start <= x);
+    let joins_left =
+        position > 0 && runs[position - 1].end == x && runs[position - 1].index == index;
+    let joins_right =
+        position < runs.len() && runs[position].start == x + 1 && runs[position].index == index;
+
+    match (joins_left, joins_right) {
+        (true, true) => {
+            runs[position - 1].end = runs[position].end;
+            runs.remove(position);
+        }
+        (true, false) => runs[position - 1].end = x + 1,
+        (false, true) => runs[position].start = x,
+        (false, false) => runs.insert(
+            position,
+            Run {
+                start: x,
+                end: x + 1,
+                index,
+            },
+        ),
+    }
+}
+
+/// Stores the runs of cells of the same item on each row, with the ids of the items interned,
+/// so its memory grows with the items and not with the size of the grid: empty cells take no
+/// memory, and empty rows only a few bytes, or none below the last item
+#[derive(Clone, Debug)]
+pub struct SparseGrid {
+    rows: usize,
+    cols: usize,
+    /// Interned ids by index, `None` for the free indexes and for index 0, the empty cells
+    ids: Vec<Option<String>>,
+    indexes: HashMap<String, u32>,
+    /// Number of cells of each index, which is freed once it gets to 0
+    counts: Vec<usize>,
+    free: Vec<u32>,
+    /// Runs of each row, sorted by their start, down to the last row holding items
+    runs: Vec<Vec<Run>>,
+}
+
+impl Default for SparseGrid {
+    fn default() -> SparseGrid {
+        SparseGrid {
+            rows: 0,
+            cols: 0,
+            ids: vec![None],
+            indexes: HashMap::new(),
+            counts: vec![0],
+            free: Vec::new(),
+            runs: Vec::new(),
+        }
+    }
+}
+
+impl SparseGrid {
+    pub fn new(rows: usize, cols: usize) -> SparseGrid {
+        let mut grid = SparseGrid::default();
+        grid.resize(rows, cols);
+        grid
+    }
+
+    fn intern(&mut self, id: &str, cells: usize) -> u32 {
+        let index = match self.indexes.get(id) {
+            Some(index) => *index,
+            None => {
+                let index = match self.free.pop() {
+                    Some(index) => index,
+                    None => {
+                        self.ids.push(None);
+                        self.counts.push(0);
+                        (self.ids.len() - 1) as u32
+                    }
+                };
+                self.ids[index as usize] = Some(id.to_string());
+                self.indexes.insert(id.to_string(), index);
+                index
+            }
+        };
+        self.counts[index as usize] += cells;
+        index
+    }
+
+    fn release(&mut self, index: u32, cells: usize) {
+        if index == 0 {
+            return;
+        }
+        let count = &mut self.counts[index as usize];
+        *count -= cells;
+        if *count == 0 {
+            if let Some(id) = self.ids[index as usize].take() {
+                self.indexes.remove(&id);
+            }
+            self.free.push(index);
+        }
+    }
+
+    /// Drops the empty rows below the last item
+    fn trim(&mut self) {
+        while self.runs.last().is_some_and(Vec::is_empty) {
+            self.runs.pop();
+        }
+    }
+
+    /// Copies the cells to a dense grid
+    pub fn to_dense(&self) -> DenseGrid {
+        let mut grid = DenseGrid::new(self.rows, self.cols);
+        for (y, runs) in self.runs.iter().enumerate() {
+            for run in runs {
+                let id = self.ids[run.index as usize].as_deref();
+                for x in run.start..run.end {
+                    grid.set(y, x, id);
+                }
+            }
+        }
+        grid
+    }
+}
+
+impl CellStorage for SparseGrid {
+    fn size(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    fn get(&self, y: usize, x: usize) -> Option<&Option<String>> {
+        if y >= self.rows || x >= self.cols {
+            return None;
+        }
+        let index = self
+            .runs
+            .get(y)
+            .and_then(|runs| Some(runs[find_run(runs, x)?].index))
+            .unwrap_or(0);
+        self.ids.get(index as usize)
+    }
+
+    fn set(&mut self, y: usize, x: usize, id: Option<&str>) -> bool {
+        if y >= self.rows || x >= self.cols {
+            return false;
+        }
+        let index = id.map_or(0, |id| self.intern(id, 1));
+        if self.runs.len() <= y {
+            self.runs.resize_with(y + 1, Vec::new);
+        }
+        let runs = &mut self.runs[y];
+
+        // Splits the run covering the cell around it
+        let old_index = match find_run(runs, x) {
+            Some(position) => {
+                let run = runs[position];
+                let left = Run { end: x, ..run };
+                let right = Run {
+                    start: x + 1,
+                    ..run
+                };
+                runs.splice(
+                    position..=position,
+                    [left, right].into_iter().filter(|run| run.start < run.end),
+                );
+                run.index
+            }
+            None => 0,
+        };
+        if index != 0 {
+            insert_cell(runs, x, index);
+        }
+        self.trim();
+
+        self.release(old_index, 1);
+        true
+    }
+
+    fn resize(&mut self, rows: usize, cols: usize) {
+        let rows = if cols == 0 { 0 } else { rows };
+
+        // Runs of the dropped rows, and the parts of the runs over the dropped columns
+        let kept_rows = rows.min(self.runs.len());
+        let mut dropped: Vec<Run> = self
+            .runs
+            .split_off(kept_rows)
+            .into_iter()
+            .flatten()
+            .collect();
+        for runs in &mut self.runs {
+            let kept = runs.partition_point(|run| run.start < cols);
+            dropped.extend(runs.drain(kept..));
+            if let Some(run) = runs.last_mut().filter(|run| run.end > cols) {
+                dropped.push(Run {
+                    start: cols,
+                    ..*run
+                });
+                run.end = cols;
+            }
+        }
+        self.trim();
+
+        for run in dropped {
+            self.release(run.index, run.end - run.start);
+        }
+        (self.rows, self.cols) = (rows, cols);
+    }
+
+    fn collisions(&self, x: usize, y: usize, w: usize, h: usize, id: &str) -> Vec<String> {
+        // First cell of each item going column by column, found from the runs crossing the
+        // rectangle, which are compared by index
+        let right = x.saturating_add(w);
+        let mut firsts: Vec<((usize, usize), u32)> = Vec::new();
+        for (row, runs) in self.runs.iter().enumerate().skip(y).take(h) {
+            let crossing = runs.partition_point(|run| run.end <= x);
+            for run in runs[crossing..].iter().take_while(|run| run.start < right) {
+                let first = (run.start.max(x), row);
+                match firsts.iter_mut().find(|(_, index)| *index == run.index) {
+                    Some((cell, _)) => *cell = (*cell).min(first),
+                    None => firsts.push((first, run.index)),
+                }
+            }
+        }
+        firsts.sort();
+
+        firsts
+            .into_iter()
+            .filter_map(|(_, index)| self.ids[index as usize].clone())
+            .filter(|cell| cell != id)
+            .collect()
+    }
+}
+
+/// Serialized form of a sparse grid, with the runs of each item as `(row, start, end, id)`
+#[derive(Serialize, Deserialize)]
+struct SerializedSparseGrid<'a> {
+    rows: usize,
+    cols: usize,
+    runs: Vec<(usize, usize, usize, Cow<'a, str>)>,
+}
+
+impl Serialize for SparseGrid {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let runs = self
+            .runs
+            .iter()
+            .enumerate()
+            .flat_map(|(y, runs)| runs.iter().map(move |run| (y, run)))
+            .filter_map(|(y, run)| {
+                let id = self.ids[run.index as usize].as_deref()?;
+                Some((y, run.start, run.end, Cow::Borrowed(id)))
+            })
+            .collect();
+        SerializedSparseGrid {
+            rows: self.rows,
+            cols: self.cols,
+            runs,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SparseGrid {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let SerializedSparseGrid { rows, cols, runs } =
+            SerializedSparseGrid::deserialize(deserializer)?;
+        let mut grid = SparseGrid::new(rows, cols);
+        for (y, start, end, id) in runs {
+            if y >= grid.rows || start >= end || end > grid.cols {
+                return Err(serde::de::Error::custom(format!(
+                    "Run of item {} on row {}, from column {} to {}, does not fit in the grid",
+                    id, y, start, end
+                )));
+            }
+            if grid.runs.len() <= y {
+                grid.runs.resize_with(y + 1, Vec::new);
+            }
+            let position = grid.runs[y].partition_point(|run| run.start < start);
+            let row = &grid.runs[y];
+            let overlaps = (position > 0 && row[position - 1].end > start)
+                || row.get(position).is_some_and(|run| run.start < end);
+            if overlaps {
+                return Err(serde::de::Error::custom(format!(
+                    "Run of item {} on row {} overlaps another run",
+                    id, y
+                )));
+            }
+
+            let index = grid.intern(&id, end - start);
+            grid.runs[y].insert(position, Run { start, end, index });
+        }
+        Ok(grid)
+    }
+}
+
+/// Backend of the cells of a grid
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StorageKind {
+    /// Every cell holds a copy of the id of its item. Fast for small grids.
+    #[default]
+    Dense,
+    /// Each row holds the runs of cells of its items. Lighter for big grids and large items, as
+    /// empty cells take no memory, and faster to find collisions, as whole runs are compared,
+    /// but looking up a cell searches the runs of its row.
+    Sparse,
+}
+
+/// The cells of a grid, in the storage chosen for the engine. They are serialized in the form of
+/// their storage, so an engine loaded from a view keeps it.
+#[derive(Clone, Debug)]
+pub enum Cells {
+    Dense(DenseGrid),
+    Sparse(SparseGrid),
+}
+
+impl Cells {
+    pub fn new(kind: StorageKind, rows: usize, cols: usize) -> Cells {
+        match kind {
+            StorageKind::Dense => Cells::Dense(DenseGrid::new(rows, cols)),
+            StorageKind::Sparse => Cells::Sparse(SparseGrid::new(rows, cols)),
+        }
+    }
+
+    pub fn kind(&self) -> StorageKind {
+        match self {
+            Cells::Dense(_) => StorageKind::Dense,
+            Cells::Sparse(_) => StorageKind::Sparse,
+        }
+    }
+}
+
+impl CellStorage for Cells {
+    fn size(&self) -> (usize, usize) {
+        match self {
+            Cells::Dense(grid) => grid.size(),
+            Cells::Sparse(grid) => grid.size(),
+        }
+    }
+
+    fn get(&self, y: usize, x: usize) -> Option<&Option<String>> {
+        match self {
+            Cells::Dense(grid) => grid.get(y, x),
+            Cells::Sparse(grid) => grid.get(y, x),
+        }
+    }
+
+    fn set(&mut self, y: usize, x: usize, id: Option<&str>) -> bool {
+        match self {
+            Cells::Dense(grid) => grid.set(y, x, id),
+            Cells::Sparse(grid) => grid.set(y, x, id),
+        }
+    }
+
+    fn resize(&mut self, rows: usize, cols: usize) {
+        match self {
+            Cells::Dense(grid) => grid.resize(rows, cols),
+            Cells::Sparse(grid) => grid.resize(rows, cols),
+        }
+    }
+
+    fn collisions(&self, x: usize, y: usize, w: usize, h: usize, id: &str) -> Vec<String> {
+        match self {
+            Cells::Dense(grid) => grid.collisions(x, y, w, h, id),
+            Cells::Sparse(grid) => grid.collisions(x, y, w, h, id),
+        }
+    }
+}
+
+impl Serialize for Cells {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Cells::Dense(grid) => grid.serialize(serializer),
+            Cells::Sparse(grid) => grid.serialize(serializer),
+        }
+    }
+}
+
+/// Cells in either serialized form, told apart by their fields
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SerializedCells {
+    Sparse(SparseGrid),
+    Dense(DenseGrid),
+}
+
+impl<'de> Deserialize<'de> for Cells {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match SerializedCells::deserialize(deserializer)? {
+            SerializedCells::Sparse(grid) => Cells::Sparse(grid),
+            SerializedCells::Dense(grid) => Cells::Dense(grid),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid_engine::GridEngine;
+    use crate::test_utils::random_generator;
+
+    use super::*;
+
+    #[test]
+    fn test_sparse_storage() {
+        let mut random = random_generator(7);
+
+        // Both storages hold the same cells and find the same collisions
+        let mut dense = DenseGrid::new(8, 8);
+        let mut sparse = SparseGrid::new(8, 8);
+        for _ in 0..500 {
+            let (y, x) = (random(10), random(10));
+            let id = ["a", "b", "c"].get(random(4)).copied();
+            assert_eq!(dense.set(y, x, id), sparse.set(y, x, id));
+            if random(20) == 0 {
+                let (rows, cols) = (1 + random(10), 1 + random(10));
+                dense.resize(rows, cols);
+                sparse.resize(rows, cols);
+            }
+
+            assert_eq!(dense.size(), sparse.size());
+            for y in 0..11 {
+                for x in 0..11 {
+                    assert_eq!(dense.get(y, x), sparse.get(y, x));
+                }
+            }
+            let (x, y, w, h) = (random(10), random(10), 1 + random(5), 1 + random(5));
+            assert_eq!(
+                dense.collisions(x, y, w, h, "a"),
+                sparse.collisions(x, y, w, h, "a")
+            );
+        }
+
+        // Each storage reads back its serialized form, and rejects runs outside of the grid
+        for cells in [Cells::Sparse(sparse.clone()), Cells::Dense(dense.clone())] {
+            let serialized = serde_json::to_string(&cells).unwrap();
+            let loaded: Cells = serde_json::from_str(&serialized).unwrap();
+            assert_eq!(loaded.kind(), cells.kind());
+            assert_eq!(loaded.size(), cells.size());
+            for (y, x) in (0..11).flat_map(|y| (0..11).map(move |x| (y, x))) {
+                assert_eq!(loaded.get(y, x), cells.get(y, x));
+            }
+        }
+        assert!(
+            serde_json::from_str::<Cells>(r#"{"rows":2,"cols":2,"runs":[[0,1,3,"a"]]}"#).is_err()
+        );
+        assert!(serde_json::from_str::<Cells>(
+            r#"{"rows":2,"cols":4,"runs":[[0,0,2,"a"],[0,1,3,"b"]]}"#
+        )
+        .is_err());
+        assert!(serde_json::from_str::<Cells>(r#"{"cols":3,"data":[null,null]}"#).is_err());
+
+        // Empty cells and rows take no memory, and ids are freed with their last cell
+        let mut sparse = SparseGrid::new(1_000_000, 1_000);
+        let item = (500..510).flat_map(|y| (300..310).map(move |x| (y, x)));
+        for (y, x) in item.clone() {
+            sparse.set(y, x, Some("a"));
+        }
+        let run = Run {
+            start: 300,
+            end: 310,
+            index: 1,
+        };
+        assert_eq!(sparse.runs.len(), 510);
+        assert!(sparse.runs[..500].iter().all(Vec::is_empty));
+        assert!(sparse.runs[500..].iter().all(|runs| runs == &[run]));
+        assert_eq!(sparse.collisions(0, 0, 1_000, 1_000_000, ""), vec!["a"]);
+        for (y, x) in item {
+            sparse.set(y, x, None);
+        }
+        assert!(sparse.runs.is_empty());
+        assert!(sparse.indexes.is_empty());
+
+        // Engines on both storages apply the same changes
+        let mut dense = GridEngine::new(6, 12);
+        let mut sparse = GridEngine::with_storage(6, 12, StorageKind::Sparse);
+        assert_eq!(sparse.get_storage_kind(), StorageKind::Sparse);
+        for step in 0..200 {
+            let id = random(8).to_string();
+            let (x, y, w, h) = (random(12), random(8), 1 + random(4), 1 + random(3));
+            let operation = random(5);
+            let results = [&mut dense, &mut sparse].map(|engine| match operation {
+                0 | 1 => engine.add_item(id.clone(), x, y, w, h).map(|_| ()),
+                2 => engine.update_item(&id, x, y, w, h),
+                3 => engine.remove_item(&id),
+                _ => engine.set_cols(4 + w * 2),
+            });
+            assert_eq!(results[0].is_ok(), results[1].is_ok(), "Step {}", step);
+            let [dense_view, sparse_view] = [&dense, &sparse].map(GridEngine::get_grid_view);
+            assert_eq!(
+                dense_view.get_grid_formatted(1),
+                sparse_view.get_grid_formatted(1),
+                "Step {}",
+                step
+            );
+            assert_eq!(
+                dense_view.get_nodes(),
+                sparse_view.get_nodes(),
+                "Step {}",
+                step
+            );
+        }
+        assert_eq!(sparse.get_hash(), dense.get_hash());
+
+        // Views keep the storage, so a loaded engine is sparse too
+        let serialized = sparse.get_grid_view().serialized_as_str();
+        let loaded: GridEngine = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(loaded.get_storage_kind(), StorageKind::Sparse);
+        assert_eq!(loaded.get_grid_view().serialized_as_str(), serialized);
+        assert_eq!(loaded.get_hash(), sparse.get_hash());
+    }
+}
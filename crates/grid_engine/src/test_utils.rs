@@ -0,0 +1,146 @@
+//! Helpers shared by the tests of the modules
+
+use std::sync::{Arc, Mutex};
+
+use proptest::prelude::*;
+
+use crate::grid_engine::{BatchChangeValue, EventName, EventValue, GridEngine, Node, NodeFlags};
+use crate::grid_view::GridView;
+
+/// Deterministic pseudo random numbers, from a linear congruential generator. Each call gets
+/// a number below `max`.
+pub(crate) fn random_generator(mut seed: u64) -> impl FnMut(usize) -> usize {
+    move |max| {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (seed >> 33) as usize % max
+    }
+}
+
+/// Records every batch the engine emits from now on
+pub(crate) fn record_batches(engine: &mut GridEngine) -> Arc<Mutex<Vec<BatchChangeValue>>> {
+    let batches = Arc::new(Mutex::new(Vec::new()));
+    let batches_clone = Arc::clone(&batches);
+    engine.events.add_listener(
+        EventName::BatchChange,
+        Box::new(move |_, event| {
+            let EventValue::BatchChange(batch) = event;
+            batches_clone.lock().unwrap().push(batch.clone());
+        }),
+    );
+    batches
+}
+
+type TestNode = (
+    u8,
+    usize,
+    usize,
+    usize,
+    usize,
+    bool,
+    u8,
+    Option<Vec<TestChild>>,
+);
+type TestChild = (u8, usize, usize, usize, usize);
+
+/// Builds a grid from generated items, skipping the ones that can not be added, with a
+/// stored layout of the generated columns when it can be added
+fn build_view(
+    (rows, cols, nodes, layout_cols): (usize, usize, Vec<TestNode>, Option<usize>),
+) -> GridView {
+    let mut engine = GridEngine::new(rows, cols);
+    for (id, x, y, w, h, locked, data, children) in nodes {
+        let id = id.to_string();
+        let mut node = Node::new(id.clone(), x, y, w, h).with_flags(NodeFlags {
+            locked,
+            no_move: false,
+        });
+        if data > 0 {
+            node = node.with_data(serde_json::json!(data));
+        }
+        if children.is_some() {
+            node = node.with_child_grid();
+        }
+        if engine.add_node(node).is_err() {
+            continue;
+        }
+
+        for (child_id, x, y, w, h) in children.unwrap_or_default() {
+            let _ = engine.update_child(&id, |child| {
+                child.add_item(child_id.to_string(), x, y, w, h).map(|_| ())
+            });
+        }
+    }
+    if let Some(layout_cols) = layout_cols {
+        let _ = engine.add_layout("sm", layout_cols);
+    }
+    engine.get_grid_view()
+}
+
+pub(crate) fn view_strategy() -> impl Strategy<Value = GridView> {
+    let child = (0..4u8, 0..4usize, 0..4usize, 1..3usize, 1..3usize);
+    let node = (
+        0..8u8,
+        0..8usize,
+        0..8usize,
+        1..4usize,
+        1..4usize,
+        proptest::bool::weighted(0.1),
+        0..3u8,
+        proptest::option::weighted(0.5, proptest::collection::vec(child, 0..4)),
+    );
+    (
+        1..9usize,
+        1..9usize,
+        proptest::collection::vec(node, 0..10),
+        proptest::option::of(1..9usize),
+    )
+        .prop_map(build_view)
+}
+
+type TestOperation = (u8, u8, u8, usize, usize, usize, usize);
+
+pub(crate) fn operations_strategy() -> impl Strategy<Value = Vec<TestOperation>> {
+    proptest::collection::vec(
+        (
+            0..7u8,
+            0..8u8,
+            0..4u8,
+            0..8usize,
+            0..8usize,
+            1..4usize,
+            1..4usize,
+        ),
+        1..8,
+    )
+}
+
+/// Runs generated operations on the engine, skipping the ones that fail
+pub(crate) fn run_operations(engine: &mut GridEngine, operations: Vec<TestOperation>) {
+    for (operation, id, child_id, x, y, w, h) in operations {
+        // Targets an existing item, or a container for the nested operations
+        let targets: Vec<String> = engine
+            .items
+            .values()
+            .filter(|node| operation < 5 || node.child.is_some())
+            .map(|node| node.id.clone())
+            .collect();
+        let id = match targets.get(id as usize % targets.len().max(1)) {
+            Some(target) if operation != 3 => target.clone(),
+            _ => id.to_string(),
+        };
+        let child_id = child_id.to_string();
+        let _ = match operation {
+            0 => engine.move_item(&id, x, y),
+            1 => engine.update_item(&id, x, y, w, h),
+            2 => engine.remove_item(&id),
+            3 => engine.add_item(id, x, y, w, h).map(|_| ()),
+            4 => engine.update_item_data(&id, serde_json::json!(x)),
+            5 => engine.update_child(&id, |child| child.move_item(&child_id, x, y)),
+            _ => engine.update_child(&id, |child| {
+                child.add_item(child_id, x, y, w, h).map(|_| ())
+            }),
+        };
+    }
+}
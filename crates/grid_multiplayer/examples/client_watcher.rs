@@ -18,7 +18,9 @@ async fn main() {
         .expect("Time went backwards");
     let client_id = since_the_epoch.as_secs() ^ since_the_epoch.subsec_nanos() as u64;
 
-    let grid_multiplayer = GridMultiplayerClient::initialize(url, client_id.to_string()).await.unwrap();
+    let _grid_multiplayer = GridMultiplayerClient::initialize(url, client_id.to_string())
+        .await
+        .unwrap();
 
     tokio::time::sleep(tokio::time::Duration::from_secs(300)).await;
 }
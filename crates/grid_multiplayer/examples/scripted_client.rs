@@ -1,12 +1,12 @@
 use std::{thread, time};
 
-use grid_engine::grid_engine::{EventName, GridEngine};
+use grid_engine::grid_engine::GridEngine;
 
 enum Interaction {
     AddItem(String, usize, usize, usize, usize),
     MoveItem(String, usize, usize),
     RemoveItem(String),
-    InvalidInteraction(String),
+    Invalid(String),
 }
 
 impl Interaction {
@@ -61,30 +61,11 @@ impl Interaction {
                     .expect("Expect y to be number");
                 Interaction::MoveItem(id.to_string(), x, y)
             }
-            _ => Interaction::InvalidInteraction(input.to_string()),
+            _ => Interaction::Invalid(input.to_string()),
         }
     }
 }
 
-#[derive(Clone, Debug)]
-struct GridContent {
-    name: String,
-}
-
-impl Default for GridContent {
-    fn default() -> Self {
-        GridContent {
-            name: "0".to_string(),
-        }
-    }
-}
-
-impl std::fmt::Display for GridContent {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.name)
-    }
-}
-
 fn print_grid(grid: &mut GridEngine) {
     // print!("\x1B[2J\x1B[1;1H");
     println!("Printing the grid");
@@ -105,7 +86,7 @@ fn handle_interaction(grid: &mut GridEngine, interaction: Interaction) {
             println!("Moving item {} to ({}, {})", &id, x, y);
             grid.move_item(&id, x, y).unwrap();
         }
-        Interaction::InvalidInteraction(instruction) => {
+        Interaction::Invalid(instruction) => {
             println!("Invalid interaction: {}", instruction);
         }
     }
@@ -142,7 +123,9 @@ async fn scripted_mode() {
         .expect("Time went backwards");
     let client_id = since_the_epoch.as_secs() ^ since_the_epoch.subsec_nanos() as u64;
 
-    let grid_multiplayer = GridMultiplayerClient::initialize(url, client_id.to_string()).await.unwrap();
+    let grid_multiplayer = GridMultiplayerClient::initialize(url, client_id.to_string())
+        .await
+        .unwrap();
 
     let instructions = vec![
         "add a 2 2 2 4 1",
@@ -2,7 +2,7 @@ use grid_multiplayer::server::GridMultiplayerServerBuilder;
 
 #[tokio::main]
 pub async fn main() {
-    let server = GridMultiplayerServerBuilder::new().start_server().await;
+    let _server = GridMultiplayerServerBuilder::new().start_server().await;
 
     // Improvised keep awake
     tokio::time::sleep(tokio::time::Duration::from_secs(300)).await;
@@ -15,7 +15,6 @@ use tokio_tungstenite::{
 use crate::{error::GridMultiplayerError, logger::Logger, message::GridDeliver};
 
 pub struct GridMultiplayerClient {
-    logger: Logger,
     pub grid_arc: Arc<Mutex<GridEngine>>,
 }
 
@@ -39,7 +38,7 @@ impl GridMultiplayerClient {
                 // If is an invalid Uri, should stop here as its caused by misconfiguration
                 return Err(GridMultiplayerError::new(
                     "Failed to parse URI",
-                    &format!("Failed to parse URI: {}", e.to_string()),
+                    &format!("Failed to parse URI: {}", e),
                     None,
                 ));
             }
@@ -54,7 +53,7 @@ impl GridMultiplayerClient {
                 // Could try to handle errors like connection closed
                 return Err(GridMultiplayerError::new(
                     "Failed to build request",
-                    &format!("Failed to build request: {}", e.to_string()),
+                    &format!("Failed to build request: {}", e),
                     None,
                 ));
             }
@@ -91,24 +90,28 @@ impl GridMultiplayerClient {
                 let external_event = match external_events_receiver.recv().await {
                     Some(event) => event,
                     None => {
-                        logger_clone.info(&format!("External event channel closed"));
+                        logger_clone.info("External event channel closed");
                         break;
                     }
                 };
 
                 match external_event {
                     EventValue::BatchChange(changes) => {
-                        logger_clone.info(&format!("Batch change received"));
+                        logger_clone.info("Batch change received");
                         let mut grid = clonned_grid_arc.lock().unwrap();
-                        grid.apply_changes(&changes.changes);
-                        logger_clone
-                            .info(&format!("{}", grid.get_grid_view().get_grid_formatted(1)));
+                        if let Err(e) = grid.apply_layout_changes(&changes.layout, &changes.changes)
+                        {
+                            logger_clone
+                                .error(&format!("Rejected batch change: {}", e.get_message()));
+                            continue;
+                        }
+                        logger_clone.info(&grid.get_grid_view().get_grid_formatted(1));
                     }
                 }
             }
         });
 
-        Ok(GridMultiplayerClient { logger, grid_arc })
+        Ok(GridMultiplayerClient { grid_arc })
         // Returns the instance
     }
 
@@ -122,23 +125,21 @@ impl GridMultiplayerClient {
             Ok(r) => r,
             Err(e) => {
                 // Could try to handle errors like connection closed, maybe a retry
-                logger.error(&format!("Failed to connect to server: {}", e.to_string()));
+                logger.error(&format!("Failed to connect to server: {}", e));
                 panic!("Failed to connect to server");
             }
         };
-        logger.info(&format!(
-            "WebSocket handshake has been successfully completed"
-        ));
+        logger.info("WebSocket handshake has been successfully completed");
 
         let (mut write, mut read) = ws_stream.split();
 
-        logger.info(&format!("Waiting for grid"));
+        logger.info("Waiting for grid");
         // Awaits for the first message to be received that is supposed to be the Grid
 
         // Should handle none received or server closed connection
         let grid_delivery = read.next().await.unwrap().unwrap().into_data();
 
-        logger.info(&format!("Grid received"));
+        logger.info("Grid received");
 
         let grid = GridDeliver::try_from(grid_delivery)
             .expect("Should never happen as the server should always send grid as first message")
@@ -157,7 +158,7 @@ impl GridMultiplayerClient {
                         external_events_sender.send(event).unwrap();
                     },
                     Some(event) = internal_events_receiver.recv() => {
-                        logger.info(&format!("Internal event received"));
+                        logger.info("Internal event received");
                         write
                         .send(Message::Binary(event.into()))
                         .await
@@ -20,7 +20,11 @@ impl GridMultiplayerError {
         }
     }
 
+    /// Message followed by the source error, or by the description when there is none
     pub fn get_message(&self) -> String {
-        format!("{}: {}", self.message, self.error.as_ref().unwrap())
+        match &self.error {
+            Some(error) => format!("{}: {}", self.message, error),
+            None => format!("{}: {}", self.message, self.description),
+        }
     }
 }
@@ -30,16 +30,7 @@ static GRID_STORAGE: LazyLock<Mutex<HashMap<String, GridView>>> =
 fn get_grid(id: &str) -> Option<GridEngine> {
     let locked = GRID_STORAGE.lock().unwrap();
     let grid = locked.get(id);
-    match grid {
-        Some(grid) => Some(GridEngine::from(grid)),
-        None => None,
-    }
-}
-
-fn save_grid(id: &str, grid: GridEngine) {
-    let grid_view = grid.get_grid_view();
-    let mut locked = GRID_STORAGE.lock().unwrap();
-    locked.insert(id.to_string(), grid_view);
+    grid.map(GridEngine::from)
 }
 
 struct Client {
@@ -63,10 +54,7 @@ impl Room {
                     return false;
                 }
 
-                match client.message.send(Message::binary(&event_value)) {
-                    Ok(_) => false,
-                    Err(_) => true,
-                }
+                client.message.send(Message::binary(&event_value)).is_err()
             })
             .map(|(_, client)| client.id.clone())
             .collect();
@@ -96,7 +84,6 @@ type CloseClosure = Box<dyn Fn()>;
 type ArcRoomsMap = Arc<Mutex<HashMap<String, ArcRoom>>>;
 
 pub struct GridMultiplayerServer {
-    rooms: ArcRoomsMap,
     pub url: String,
 }
 
@@ -106,6 +93,12 @@ pub struct GridMultiplayerServerBuilder {
     logger: Logger,
 }
 
+impl Default for GridMultiplayerServerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl GridMultiplayerServerBuilder {
     pub fn new() -> GridMultiplayerServerBuilder {
         GridMultiplayerServerBuilder {
@@ -147,27 +140,28 @@ impl GridMultiplayerServerBuilder {
         // Let's spawn the handling of each connection in a separate task.
 
         GridMultiplayerServer {
-            rooms: rooms,
             url: format!("ws://{}", addr),
         }
     }
 
-    pub fn on_change(&mut self, closure: ChangeClosure) -> () {
+    pub fn on_change(&mut self, closure: ChangeClosure) {
         self.change_closures.push(closure);
     }
 
-    pub fn on_close(&mut self, closure: CloseClosure) -> () {
+    pub fn on_close(&mut self, closure: CloseClosure) {
         self.close_closures.push(closure);
     }
 }
 
+// The handshake callback error type is defined by tungstenite
+#[allow(clippy::result_large_err)]
 async fn handle_connection(
     rooms: ArcRoomsMap,
     raw_stream: TcpStream,
     addr: SocketAddr,
     logger: Logger,
 ) {
-    logger.info(&format!("Incoming TCP connection"));
+    logger.info("Incoming TCP connection");
 
     let (grid_id_sender, grid_id_receiver) = tokio::sync::oneshot::channel::<String>();
 
@@ -191,8 +185,8 @@ async fn handle_connection(
                 match unlocked_room.get(&grid_id) {
                     Some(room) => {
                         logger.info("Room already exists");
-                        let clone = Arc::clone(&room);
-                        clone
+
+                        Arc::clone(room)
                     }
                     None => {
                         logger.info("Creating new room");
@@ -282,16 +276,13 @@ async fn handle_connection(
         grid.into()
     };
 
-    match ws_out.send(Message::binary(grid_binary)).await {
-        Err(e) => {
-            // Should handle possibly connection closed, or just assume that if this error happens the connection is closed
-            logger.error(&format!("Error sending grid to client: {}", e));
-            let room = rooms.lock().unwrap();
-            let mut room = room.get(&grid_id).unwrap().lock().unwrap();
-            room.close_connection(&client_id);
-            return;
-        }
-        Ok(_) => {}
+    if let Err(e) = ws_out.send(Message::binary(grid_binary)).await {
+        // Should handle possibly connection closed, or just assume that if this error happens the connection is closed
+        logger.error(&format!("Error sending grid to client: {}", e));
+        let room = rooms.lock().unwrap();
+        let mut room = room.get(&grid_id).unwrap().lock().unwrap();
+        room.close_connection(&client_id);
+        return;
     };
     logger.info("Grid send");
 
@@ -345,11 +336,14 @@ async fn handle_connection(
                 match event {
                     EventValue::BatchChange(changes) => {
                         logger.info("Applying external change");
-                        if changes.hash_after == room.grid.get_grid_view().hash() {
+                        if changes.hash_after == room.grid.get_hash() {
                             logger.error("Hash mismatch");
                             continue;
                         }
-                        room.grid.apply_changes(&changes.changes);
+                        if let Err(e) = room.grid.apply_layout_changes(&changes.layout, &changes.changes) {
+                            logger.error(&format!("Rejected external change: {}", e.get_message()));
+                            continue;
+                        }
                         logger.info(&format!(
                             "\n {}",
                             room.grid.get_grid_view().get_grid_formatted(1)
@@ -1,5 +1,11 @@
+pub use grid_engine::batch;
+pub use grid_engine::diff::{LayoutChanges, ViewDiff};
+pub use grid_engine::error::GridError;
+pub use grid_engine::free_space::*;
 pub use grid_engine::grid_engine::*;
 pub use grid_engine::grid_view::*;
+pub use grid_engine::query::*;
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
 extern crate console_error_panic_hook;
 
@@ -13,10 +19,50 @@ extern "C" {
     #[derive(Debug)]
     pub type Changes;
 
+    #[wasm_bindgen(typescript_type = "ViewDiff")]
+    #[derive(Debug)]
+    pub type ViewDiffValue;
+
+    #[wasm_bindgen(typescript_type = "SizeConstraints")]
+    #[derive(Debug)]
+    pub type SizeConstraintsValue;
+
+    #[wasm_bindgen(typescript_type = "NodeFlags")]
+    #[derive(Debug)]
+    pub type NodeFlagsValue;
+
+    #[wasm_bindgen(typescript_type = "Array<[number, number]>")]
+    #[derive(Debug)]
+    pub type Positions;
+
+    #[wasm_bindgen(typescript_type = "Neighbors")]
+    #[derive(Debug)]
+    pub type NeighborsValue;
+
+    #[wasm_bindgen(typescript_type = "Rect | undefined")]
+    #[derive(Debug)]
+    pub type RectValue;
+
+    #[wasm_bindgen(typescript_type = "Rect[]")]
+    #[derive(Debug)]
+    pub type Rects;
+
+    #[wasm_bindgen(typescript_type = "Array<Array<Array<[number, number]>>>")]
+    #[derive(Debug)]
+    pub type FitMapValue;
+
     #[wasm_bindgen(js_namespace = console)]
     fn log(s: &str);
 }
 
+/// Converts the error to a JS `Error` named after the `GridError` variant, like `ItemNotFound`,
+/// so that it can be matched on
+fn to_js_error(error: GridError) -> JsValue {
+    let js_error = js_sys::Error::new(&error.get_message());
+    js_error.set_name(error.kind());
+    js_error.into()
+}
+
 #[wasm_bindgen]
 pub struct GridViewWasm {
     grid_view: GridView,
@@ -35,6 +81,85 @@ impl GridViewWasm {
         self.grid_view.get_nodes()
     }
 
+    /// Gets the data of the item as a plain JS value, undefined if it has none
+    #[wasm_bindgen(js_name = getItemData)]
+    pub fn get_item_data(&self, id: &str) -> Result<JsValue, JsValue> {
+        let node = match self
+            .grid_view
+            .get_nodes()
+            .into_iter()
+            .find(|node| node.id == id)
+        {
+            Some(node) => node,
+            None => return Err(to_js_error(GridError::ItemNotFound { id: id.to_string() })),
+        };
+        if node.data.is_null() {
+            return Ok(JsValue::UNDEFINED);
+        }
+        // Objects become plain objects instead of maps
+        let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+        Ok(node.data.serialize(&serializer)?)
+    }
+
+    #[wasm_bindgen(js_name = itemAt)]
+    pub fn item_at(&self, x: usize, y: usize) -> Option<Node> {
+        self.grid_view.item_at(x, y).cloned()
+    }
+
+    #[wasm_bindgen(js_name = itemsInRect)]
+    pub fn items_in_rect(&self, x: usize, y: usize, w: usize, h: usize) -> Vec<Node> {
+        self.grid_view
+            .items_in_rect(x, y, w, h)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    #[wasm_bindgen(js_name = neighbors)]
+    pub fn neighbors(&self, id: &str) -> Result<NeighborsValue, JsValue> {
+        match self.grid_view.neighbors(id) {
+            Ok(neighbors) => Ok(serde_wasm_bindgen::to_value(&neighbors)?.unchecked_into()),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = boundingBox)]
+    pub fn bounding_box(&self) -> Result<RectValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.grid_view.bounding_box())?.unchecked_into())
+    }
+
+    #[wasm_bindgen(js_name = occupancy)]
+    pub fn occupancy(&self) -> f64 {
+        self.grid_view.occupancy()
+    }
+
+    #[wasm_bindgen(js_name = maximalEmptyRects)]
+    pub fn maximal_empty_rects(&self) -> Result<Rects, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.grid_view.maximal_empty_rects())?.unchecked_into())
+    }
+
+    /// Gets, by row and column, the largest `[w, h]` sizes that fit with their top-left corner
+    /// on each cell
+    #[wasm_bindgen(js_name = fitMap)]
+    pub fn fit_map(&self) -> Result<FitMapValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.grid_view.fit_map().cells)?.unchecked_into())
+    }
+
+    #[wasm_bindgen(js_name = canFit)]
+    pub fn can_fit(&self, w: usize, h: usize) -> bool {
+        self.grid_view.can_fit(w, h)
+    }
+
+    /// Gets the changes turning this grid into `to`, the ones of the active layout to be applied
+    /// with `applyChanges`, then the ones of each stored layout with `applyLayoutChanges`
+    #[wasm_bindgen(js_name = diff)]
+    pub fn diff(&self, to: &GridViewWasm) -> Result<ViewDiffValue, JsValue> {
+        match self.grid_view.diff(&to.grid_view) {
+            Ok(diff) => Ok(serde_wasm_bindgen::to_value(&diff)?.unchecked_into()),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
     #[wasm_bindgen(js_name = printGrid)]
     pub fn print_grid(&self) {
         self.grid_view.print_grid();
@@ -50,12 +175,35 @@ impl GridViewWasm {
         self.grid_view.serialized_as_str()
     }
 
+    #[wasm_bindgen(js_name = getLayout)]
+    pub fn get_layout(&self) -> String {
+        self.grid_view.get_layout().to_string()
+    }
+
     #[wasm_bindgen(js_name = hash)]
     pub fn hash(&self) -> String {
         self.grid_view.hash()
     }
 }
 
+#[wasm_bindgen]
+pub struct PreviewWasm {
+    preview: Preview,
+}
+
+#[wasm_bindgen]
+impl PreviewWasm {
+    #[wasm_bindgen(js_name = getChanges)]
+    pub fn get_changes(&self) -> Result<Changes, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.preview.changes)?.unchecked_into())
+    }
+
+    #[wasm_bindgen(js_name = getGridView)]
+    pub fn get_grid_view(&self) -> GridViewWasm {
+        GridViewWasm::from_grid_view(&self.preview.grid_view)
+    }
+}
+
 #[wasm_bindgen]
 pub struct GridEngineWasm {
     grid_engine: GridEngine,
@@ -66,7 +214,7 @@ impl GridEngineWasm {
     #[wasm_bindgen(constructor)]
     pub fn new(rows: usize, cols: usize) -> GridEngineWasm {
         console_error_panic_hook::set_once();
-        
+
         GridEngineWasm {
             grid_engine: GridEngine::new(rows, cols),
         }
@@ -80,26 +228,377 @@ impl GridEngineWasm {
         y: usize,
         w: usize,
         h: usize,
-    ) -> Result<String, JsError> {
+    ) -> Result<String, JsValue> {
         match self.grid_engine.add_item(id, x, y, w, h) {
             Ok(id) => Ok(id),
-            Err(e) => Err(JsError::new(&e.get_message())),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = addItemWithConstraints)]
+    pub fn add_item_with_constraints(
+        &mut self,
+        id: String,
+        x: usize,
+        y: usize,
+        w: usize,
+        h: usize,
+        constraints: SizeConstraintsValue,
+    ) -> Result<String, JsValue> {
+        let constraints: SizeConstraints = serde_wasm_bindgen::from_value(constraints.obj)?;
+        let node = Node::new(id, x, y, w, h).with_size_constraints(constraints);
+        match self.grid_engine.add_node(node) {
+            Ok(id) => Ok(id),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = addItemWithFlags)]
+    pub fn add_item_with_flags(
+        &mut self,
+        id: String,
+        x: usize,
+        y: usize,
+        w: usize,
+        h: usize,
+        flags: NodeFlagsValue,
+    ) -> Result<String, JsValue> {
+        let flags: NodeFlags = serde_wasm_bindgen::from_value(flags.obj)?;
+        let node = Node::new(id, x, y, w, h).with_flags(flags);
+        match self.grid_engine.add_node(node) {
+            Ok(id) => Ok(id),
+            Err(e) => Err(to_js_error(e)),
         }
     }
 
+    #[wasm_bindgen(js_name = addItemWithData)]
+    pub fn add_item_with_data(
+        &mut self,
+        id: String,
+        x: usize,
+        y: usize,
+        w: usize,
+        h: usize,
+        data: JsValue,
+    ) -> Result<String, JsValue> {
+        let node = Node::new(id, x, y, w, h).with_data(serde_wasm_bindgen::from_value(data)?);
+        match self.grid_engine.add_node(node) {
+            Ok(id) => Ok(id),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = updateItemData)]
+    pub fn update_item_data(&mut self, id: &str, data: JsValue) -> Result<(), JsValue> {
+        let data = serde_wasm_bindgen::from_value(data)?;
+        match self.grid_engine.update_item_data(id, data) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = addItemAuto)]
+    pub fn add_item_auto(&mut self, id: String, w: usize, h: usize) -> Result<String, JsValue> {
+        match self.grid_engine.add_item_auto(id, w, h) {
+            Ok(id) => Ok(id),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = getFreePositions)]
+    pub fn get_free_positions(&self, w: usize, h: usize) -> Result<Positions, JsValue> {
+        let positions = self.grid_engine.get_free_positions(w, h);
+        Ok(serde_wasm_bindgen::to_value(&positions)?.unchecked_into())
+    }
+
     #[wasm_bindgen(js_name = moveItem)]
-    pub fn move_item(&mut self, id: &str, x: usize, y: usize) -> Result<(), JsError> {
+    pub fn move_item(&mut self, id: &str, x: usize, y: usize) -> Result<(), JsValue> {
         match self.grid_engine.move_item(id, x, y) {
             Ok(_) => Ok(()),
-            Err(e) => Err(JsError::new(&e.get_message())),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = resizeItem)]
+    pub fn resize_item(&mut self, id: &str, w: usize, h: usize) -> Result<(), JsValue> {
+        match self.grid_engine.resize_item(id, w, h) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = updateItem)]
+    pub fn update_item(
+        &mut self,
+        id: &str,
+        x: usize,
+        y: usize,
+        w: usize,
+        h: usize,
+    ) -> Result<(), JsValue> {
+        match self.grid_engine.update_item(id, x, y, w, h) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = addItemWithChildGrid)]
+    pub fn add_item_with_child_grid(
+        &mut self,
+        id: String,
+        x: usize,
+        y: usize,
+        w: usize,
+        h: usize,
+    ) -> Result<String, JsValue> {
+        let node = Node::new(id, x, y, w, h).with_child_grid();
+        match self.grid_engine.add_node(node) {
+            Ok(id) => Ok(id),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    /// Gets the grid nested in the item at the path, like "tabs/panel"
+    #[wasm_bindgen(js_name = getChild)]
+    pub fn get_child(&self, path: &str) -> Result<Option<GridViewWasm>, JsValue> {
+        match self.grid_engine.get_child(path) {
+            Ok(child) => {
+                Ok(child.map(|child| GridViewWasm::from_grid_view(&child.get_grid_view())))
+            }
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = addChildItem)]
+    pub fn add_child_item(
+        &mut self,
+        path: &str,
+        id: String,
+        x: usize,
+        y: usize,
+        w: usize,
+        h: usize,
+    ) -> Result<(), JsValue> {
+        match self
+            .grid_engine
+            .update_child(path, |child| child.add_item(id, x, y, w, h).map(|_| ()))
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = removeChildItem)]
+    pub fn remove_child_item(&mut self, path: &str, id: &str) -> Result<(), JsValue> {
+        match self
+            .grid_engine
+            .update_child(path, |child| child.remove_item(id))
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = updateChildItem)]
+    pub fn update_child_item(
+        &mut self,
+        path: &str,
+        id: &str,
+        x: usize,
+        y: usize,
+        w: usize,
+        h: usize,
+    ) -> Result<(), JsValue> {
+        match self
+            .grid_engine
+            .update_child(path, |child| child.update_item(id, x, y, w, h))
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = moveIntoChild)]
+    pub fn move_into_child(
+        &mut self,
+        id: &str,
+        path: &str,
+        x: usize,
+        y: usize,
+    ) -> Result<(), JsValue> {
+        match self.grid_engine.move_into_child(id, path, x, y) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = moveOutOfChild)]
+    pub fn move_out_of_child(
+        &mut self,
+        path: &str,
+        id: &str,
+        x: usize,
+        y: usize,
+    ) -> Result<(), JsValue> {
+        match self.grid_engine.move_out_of_child(path, id, x, y) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = previewAddItem)]
+    pub fn preview_add_item(
+        &self,
+        id: String,
+        x: usize,
+        y: usize,
+        w: usize,
+        h: usize,
+    ) -> Result<PreviewWasm, JsValue> {
+        match self.grid_engine.preview_add_item(id, x, y, w, h) {
+            Ok(preview) => Ok(PreviewWasm { preview }),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = previewMoveItem)]
+    pub fn preview_move_item(&self, id: &str, x: usize, y: usize) -> Result<PreviewWasm, JsValue> {
+        match self.grid_engine.preview_move_item(id, x, y) {
+            Ok(preview) => Ok(PreviewWasm { preview }),
+            Err(e) => Err(to_js_error(e)),
         }
     }
 
     #[wasm_bindgen(js_name = removeItem)]
-    pub fn remove_item(&mut self, id: &str) -> Result<(), JsError> {
+    pub fn remove_item(&mut self, id: &str) -> Result<(), JsValue> {
         match self.grid_engine.remove_item(id) {
             Ok(_) => Ok(()),
-            Err(e) => Err(JsError::new(&e.get_message())),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = setCollisionStrategy)]
+    pub fn set_collision_strategy(&mut self, collision_strategy: CollisionStrategy) {
+        self.grid_engine.set_collision_strategy(collision_strategy);
+    }
+
+    #[wasm_bindgen(js_name = setSwap)]
+    pub fn set_swap(&mut self, swap: bool) {
+        self.grid_engine.set_swap(swap);
+    }
+
+    #[wasm_bindgen(js_name = setFloat)]
+    pub fn set_float(&mut self, float: bool) {
+        self.grid_engine.set_float(float);
+    }
+
+    #[wasm_bindgen(js_name = setMaxRows)]
+    pub fn set_max_rows(&mut self, max_rows: Option<usize>) {
+        self.grid_engine.set_max_rows(max_rows);
+    }
+
+    #[wasm_bindgen(js_name = setCols)]
+    pub fn set_cols(&mut self, cols: usize) -> Result<(), JsValue> {
+        match self.grid_engine.set_cols(cols) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = beginTransaction)]
+    pub fn begin_transaction(&mut self) -> Result<(), JsValue> {
+        match self.grid_engine.begin_transaction() {
+            Ok(_) => Ok(()),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = commit)]
+    pub fn commit(&mut self) -> Result<(), JsValue> {
+        match self.grid_engine.commit() {
+            Ok(_) => Ok(()),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = rollback)]
+    pub fn rollback(&mut self) -> Result<(), JsValue> {
+        match self.grid_engine.rollback() {
+            Ok(_) => Ok(()),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = undo)]
+    pub fn undo(&mut self) -> Result<bool, JsValue> {
+        match self.grid_engine.undo() {
+            Ok(undone) => Ok(undone),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = redo)]
+    pub fn redo(&mut self) -> Result<bool, JsValue> {
+        match self.grid_engine.redo() {
+            Ok(redone) => Ok(redone),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = canUndo)]
+    pub fn can_undo(&self) -> bool {
+        self.grid_engine.can_undo()
+    }
+
+    #[wasm_bindgen(js_name = canRedo)]
+    pub fn can_redo(&self) -> bool {
+        self.grid_engine.can_redo()
+    }
+
+    #[wasm_bindgen(js_name = setHistoryDepth)]
+    pub fn set_history_depth(&mut self, depth: usize) {
+        self.grid_engine.set_history_depth(depth);
+    }
+
+    #[wasm_bindgen(js_name = getLayout)]
+    pub fn get_layout(&self) -> String {
+        self.grid_engine.get_layout().to_string()
+    }
+
+    #[wasm_bindgen(js_name = getLayoutNames)]
+    pub fn get_layout_names(&self) -> Vec<String> {
+        self.grid_engine.get_layout_names()
+    }
+
+    #[wasm_bindgen(js_name = addLayout)]
+    pub fn add_layout(&mut self, name: &str, cols: usize) -> Result<(), JsValue> {
+        match self.grid_engine.add_layout(name, cols) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = setLayout)]
+    pub fn set_layout(&mut self, name: &str) -> Result<(), JsValue> {
+        match self.grid_engine.set_layout(name) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = removeLayout)]
+    pub fn remove_layout(&mut self, name: &str) -> Result<(), JsValue> {
+        match self.grid_engine.remove_layout(name) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = compact)]
+    pub fn compact(&mut self) -> Result<(), JsValue> {
+        match self.grid_engine.compact() {
+            Ok(_) => Ok(()),
+            Err(e) => Err(to_js_error(e)),
         }
     }
 
@@ -108,6 +607,12 @@ impl GridEngineWasm {
         GridViewWasm::from_grid_view(&self.grid_engine.get_grid_view())
     }
 
+    /// Same as the hash of the grid view, without copying the grid
+    #[wasm_bindgen(js_name = getHash)]
+    pub fn get_hash(&self) -> String {
+        self.grid_engine.get_hash()
+    }
+
     #[wasm_bindgen(js_name = getNodes)] // Should remove this as this can be done via getGridView
     pub fn get_nodes(&self) -> Vec<Node> {
         self.grid_engine.get_grid_view().get_nodes()
@@ -119,12 +624,23 @@ impl GridEngineWasm {
     }
 
     #[wasm_bindgen(js_name = applyChanges)]
-    pub fn apply_changes(&mut self, changes: Changes) -> Result<(), JsError> {
+    pub fn apply_changes(&mut self, changes: Changes) -> Result<(), JsValue> {
         // log(&format!("Args received, {:#?}", changes));
         let changes: Vec<Change> = serde_wasm_bindgen::from_value(changes.obj)?;
         // log(&format!("Changes parsed, {:#?}", changes));
-        self.grid_engine.apply_changes(&changes);
-        Ok(())
+        match self.grid_engine.apply_changes(&changes) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(to_js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = applyLayoutChanges)]
+    pub fn apply_layout_changes(&mut self, layout: &str, changes: Changes) -> Result<(), JsValue> {
+        let changes: Vec<Change> = serde_wasm_bindgen::from_value(changes.obj)?;
+        match self.grid_engine.apply_layout_changes(layout, &changes) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(to_js_error(e)),
+        }
     }
 
     #[wasm_bindgen(js_name = serializedAsStr)] // Should remove this as this can be done via getGridView
@@ -133,23 +649,21 @@ impl GridEngineWasm {
     }
 
     #[wasm_bindgen(js_name = fromSerializedStr)]
-    pub fn from_serialized_str(serialized_str: &str) -> Result<GridEngineWasm, JsError> {
+    pub fn from_serialized_str(serialized_str: &str) -> Result<GridEngineWasm, JsValue> {
         console_error_panic_hook::set_once();
-        
+
         let as_bytes_str = serialized_str.as_bytes().to_vec();
         match GridEngine::try_from(&as_bytes_str) {
-            Ok(grid_engine) => Ok(GridEngineWasm {
-                grid_engine: grid_engine,
-            }),
-            Err(e) => Err(JsError::new(&e.get_message())),
+            Ok(grid_engine) => Ok(GridEngineWasm { grid_engine }),
+            Err(e) => Err(to_js_error(e)),
         }
     }
 
     #[wasm_bindgen(js_name = addEventListener)]
     pub fn add_event_listener(
         &mut self,
-        event_name: EventName,
-        listener_callback: EventListenerCallback,
+        _event_name: EventName,
+        _listener_callback: EventListenerCallback,
     ) {
         // self.grid_engine.events.add_listener(
         //     event_name.clone(),
@@ -176,3 +690,32 @@ impl GridEngineWasm {
         // );
     }
 }
+
+/// Merges the changes of each item into the fewest changes with the same result
+#[wasm_bindgen(js_name = squashChanges)]
+pub fn squash_changes(changes: Changes) -> Result<Changes, JsValue> {
+    let changes: Vec<Change> = serde_wasm_bindgen::from_value(changes.obj)?;
+    match batch::squash(&changes) {
+        Ok(changes) => Ok(serde_wasm_bindgen::to_value(&changes)?.unchecked_into()),
+        Err(e) => Err(to_js_error(e)),
+    }
+}
+
+/// Gets the changes reverting the given ones
+#[wasm_bindgen(js_name = invertChanges)]
+pub fn invert_changes(changes: Changes) -> Result<Changes, JsValue> {
+    let changes: Vec<Change> = serde_wasm_bindgen::from_value(changes.obj)?;
+    Ok(serde_wasm_bindgen::to_value(&batch::invert(&changes))?.unchecked_into())
+}
+
+/// Merges two batches of changes, the second one applied after the first one, into a single
+/// squashed batch
+#[wasm_bindgen(js_name = composeChanges)]
+pub fn compose_changes(first: Changes, second: Changes) -> Result<Changes, JsValue> {
+    let first: Vec<Change> = serde_wasm_bindgen::from_value(first.obj)?;
+    let second: Vec<Change> = serde_wasm_bindgen::from_value(second.obj)?;
+    match batch::compose(&first, &second) {
+        Ok(changes) => Ok(serde_wasm_bindgen::to_value(&changes)?.unchecked_into()),
+        Err(e) => Err(to_js_error(e)),
+    }
+}
@@ -72,7 +72,7 @@ export class GridMultiplayerClient {
       });
 
       socket.on("changes", (changes: EventValue["value"]) => {
-        const actualHash = grid.getGridView().hash();
+        const actualHash = grid.getHash();
         opts.logger.info("Changes received");
         opts.logger.info(
           JSON.stringify({
@@ -88,7 +88,10 @@ export class GridMultiplayerClient {
           changes.hash_after !== actualHash
         ) {
           opts.logger.info("Applying change");
-          grid.applyChanges(changes.changes);
+          grid.applyLayoutChanges(
+            changes.layout ?? "default",
+            changes.changes
+          );
         } else if (changes.hash_after === actualHash) {
           // Duplicated
           opts.logger.warn("Already applied change");
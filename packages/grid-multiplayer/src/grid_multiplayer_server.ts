@@ -84,10 +84,10 @@ export class GridMultiplayerServer {
 
       socket.on("changes", (changes: EventValue['value']) => {
         console.log("Socket Received Change", changes);
-        const actualHash = grid.getGridView().hash();
+        const actualHash = grid.getHash();
         if (changes.hash_before === actualHash && changes.hash_after !== actualHash) {
           console.log("Applying changes")
-          grid.applyChanges(changes.changes);
+          grid.applyLayoutChanges(changes.layout ?? "default", changes.changes);
         } else if (changes.hash_after === actualHash) {
           // Duplicated
           console.log("Received already applied change");